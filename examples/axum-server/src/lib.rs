@@ -14,7 +14,7 @@ use wasi_hyperium::{
 };
 
 wit_bindgen::generate!({
-    path: "../../wit",
+    path: "../../wit/0.2.0-rc-2023-11-10",
     world: "incoming",
     exports: {
         "wasi:http/incoming-handler": Guest,
@@ -0,0 +1,139 @@
+use std::{
+    cell::Cell,
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+/// The number of stream operations a task may perform each time it is polled
+/// by an executor in this crate before it is made to yield.
+pub(crate) const INITIAL_BUDGET: u8 = 128;
+
+thread_local! {
+    // None outside of executors in this crate, where operations are unbudgeted
+    static BUDGET: Cell<Option<u8>> = const { Cell::new(None) };
+}
+
+/// Returns a future which yields to the executor once before completing,
+/// giving other tasks and pollables a chance to make progress.
+pub fn yield_now() -> YieldNow {
+    YieldNow { yielded: false }
+}
+
+pub struct YieldNow {
+    yielded: bool,
+}
+
+impl Future for YieldNow {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        if self.yielded {
+            return Poll::Ready(());
+        }
+        self.yielded = true;
+        cx.waker().wake_by_ref();
+        Poll::Pending
+    }
+}
+
+/// Consumes one unit of the current task's budget. Once the budget is
+/// exhausted this wakes the task and returns Pending, so that the task yields
+/// to the executor even if every operation it polls is ready.
+pub fn poll_proceed(cx: &mut Context) -> Poll<()> {
+    BUDGET.with(|budget| match budget.get() {
+        None => Poll::Ready(()),
+        Some(0) => {
+            cx.waker().wake_by_ref();
+            Poll::Pending
+        }
+        Some(remaining) => {
+            budget.set(Some(remaining - 1));
+            Poll::Ready(())
+        }
+    })
+}
+
+/// Runs the given function, usually a single poll of a task, with a fresh
+/// budget.
+pub(crate) fn with_budget<R>(f: impl FnOnce() -> R) -> R {
+    struct Reset(Option<u8>);
+
+    impl Drop for Reset {
+        fn drop(&mut self) {
+            BUDGET.with(|budget| budget.set(self.0));
+        }
+    }
+
+    let _reset = Reset(BUDGET.with(|budget| budget.replace(Some(INITIAL_BUDGET))));
+    f()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        cell::Cell,
+        future::{poll_fn, Future},
+        pin::pin,
+        sync::Arc,
+        task::{Context, Waker},
+    };
+
+    use crate::{
+        executor::LocalExecutor,
+        poll::{PollableRegistry, Poller, WakeFlag},
+        testing::MockPollable,
+    };
+
+    use super::{poll_proceed, with_budget, yield_now, INITIAL_BUDGET};
+
+    #[test]
+    fn yield_now_wakes_itself_once() {
+        let woken = Arc::new(WakeFlag::new(false));
+        let waker = Waker::from(woken.clone());
+        let mut cx = Context::from_waker(&waker);
+        let mut fut = pin!(yield_now());
+        assert!(fut.as_mut().poll(&mut cx).is_pending());
+        assert!(woken.take());
+        assert!(fut.as_mut().poll(&mut cx).is_ready());
+        assert!(!woken.is_set());
+    }
+
+    #[test]
+    fn yield_now_completes_under_executors() {
+        // Nothing is registered, so these only complete if the yield wakes
+        // its task
+        let polls = &Cell::new(0);
+        let counted = || {
+            let mut fut = Box::pin(yield_now());
+            poll_fn(move |cx| {
+                polls.set(polls.get() + 1);
+                fut.as_mut().poll(cx)
+            })
+        };
+        let poller = Poller::<MockPollable>::default();
+        poller.block_on(counted()).unwrap();
+        assert_eq!(polls.get(), 2);
+
+        polls.set(0);
+        LocalExecutor::new(poller).run_until(counted()).unwrap();
+        assert_eq!(polls.get(), 2);
+    }
+
+    #[test]
+    fn poll_proceed_is_unbudgeted_outside_with_budget() {
+        let waker = Waker::from(Arc::new(WakeFlag::new(false)));
+        let mut cx = Context::from_waker(&waker);
+        for _ in 0..=INITIAL_BUDGET {
+            assert!(poll_proceed(&mut cx).is_ready());
+        }
+
+        with_budget(|| {
+            for _ in 0..INITIAL_BUDGET {
+                assert!(poll_proceed(&mut cx).is_ready());
+            }
+            assert!(poll_proceed(&mut cx).is_pending());
+        });
+        assert!(poll_proceed(&mut cx).is_ready());
+    }
+}
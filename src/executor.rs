@@ -0,0 +1,309 @@
+use std::{
+    cell::RefCell,
+    collections::VecDeque,
+    future::Future,
+    pin::Pin,
+    rc::Rc,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    task::{Context, Poll, Wake, Waker},
+};
+
+use crate::{
+    coop,
+    poll::{PollableRegistry, Stalled, WakeFlag},
+};
+
+/// A single-threaded executor which drives any number of spawned tasks
+/// alongside a main future, polling WASI pollables through its registry
+/// whenever no task is ready to make progress. This is a cheaply-`clone`able
+/// handle to its underlying state.
+pub struct LocalExecutor<Registry> {
+    inner: Rc<Inner<Registry>>,
+}
+
+struct Inner<Registry> {
+    registry: Registry,
+    tasks: RefCell<Vec<Option<Task>>>,
+    free: RefCell<Vec<usize>>,
+    ready: Arc<ReadyQueue>,
+}
+
+type TaskFuture = Pin<Box<dyn Future<Output = ()>>>;
+
+struct Task {
+    // None while the task is being polled
+    future: Option<TaskFuture>,
+    waker: Arc<TaskWaker>,
+}
+
+impl<Registry: PollableRegistry> LocalExecutor<Registry> {
+    pub fn new(registry: Registry) -> Self {
+        Self {
+            inner: Rc::new(Inner {
+                registry,
+                tasks: Default::default(),
+                free: Default::default(),
+                ready: Default::default(),
+            }),
+        }
+    }
+
+    pub fn registry(&self) -> &Registry {
+        &self.inner.registry
+    }
+
+    /// Spawns a task onto this executor. The task makes progress whenever
+    /// [`LocalExecutor::run_until`] is running; dropping the returned
+    /// [`JoinHandle`] detaches the task.
+    pub fn spawn_local<F>(&self, fut: F) -> JoinHandle<F::Output>
+    where
+        F: Future + 'static,
+        F::Output: 'static,
+    {
+        let state = Rc::new(RefCell::new(JoinState::Pending(None)));
+        let handle = JoinHandle {
+            state: state.clone(),
+        };
+        let future = Box::pin(async move {
+            let output = fut.await;
+            let prev = std::mem::replace(&mut *state.borrow_mut(), JoinState::Ready(output));
+            if let JoinState::Pending(Some(waker)) = prev {
+                waker.wake();
+            }
+        });
+
+        let mut tasks = self.inner.tasks.borrow_mut();
+        let id = self.inner.free.borrow_mut().pop().unwrap_or(tasks.len());
+        let waker = Arc::new(TaskWaker {
+            id,
+            scheduled: AtomicBool::new(false),
+            ready: self.inner.ready.clone(),
+        });
+        waker.wake_by_ref();
+        let task = Task {
+            future: Some(future),
+            waker,
+        };
+        if id == tasks.len() {
+            tasks.push(Some(task));
+        } else {
+            tasks[id] = Some(task);
+        }
+        handle
+    }
+
+    /// Runs spawned tasks until the given future completes. Returns
+    /// Err(Stalled) if there are no active pollables while the future and all
+    /// tasks are pending.
+    pub fn run_until<T>(&self, fut: impl Future<Output = T>) -> Result<T, Stalled> {
+        let mut fut = std::pin::pin!(fut);
+        let main = Arc::new(WakeFlag::new(true));
+        let waker = main.clone().into();
+        let mut cx = Context::from_waker(&waker);
+        loop {
+            if main.take() {
+                if let Poll::Ready(val) = coop::with_budget(|| fut.as_mut().poll(&mut cx)) {
+                    return Ok(val);
+                }
+            }
+            self.run_ready_tasks();
+            if main.is_set() || !self.inner.ready.is_empty() {
+                // Don't block, but let tasks waiting on pollables progress too
+                self.inner.registry.poll_nonblocking();
+                continue;
+            }
+            if !self.inner.registry.poll() {
+                self.inner.registry.report_stall();
+                return Err(Stalled);
+            }
+        }
+    }
+
+    /// Polls each task that was ready when this was called once.
+    fn run_ready_tasks(&self) {
+        let ready = self.inner.ready.take();
+        for id in ready {
+            let Some((mut future, waker)) = self.take_task(id) else {
+                continue;
+            };
+            waker.scheduled.store(false, Ordering::SeqCst);
+            let task_waker = waker.clone().into();
+            let mut cx = Context::from_waker(&task_waker);
+            // NOTE: tasks must not be borrowed here; the task may spawn others
+            let poll = coop::with_budget(|| future.as_mut().poll(&mut cx));
+            let mut tasks = self.inner.tasks.borrow_mut();
+            if poll.is_ready() {
+                tasks[id] = None;
+                self.inner.free.borrow_mut().push(id);
+            } else {
+                tasks[id].as_mut().unwrap().future = Some(future);
+            }
+        }
+    }
+
+    fn take_task(&self, id: usize) -> Option<(TaskFuture, Arc<TaskWaker>)> {
+        let mut tasks = self.inner.tasks.borrow_mut();
+        let task = tasks.get_mut(id)?.as_mut()?;
+        Some((task.future.take()?, task.waker.clone()))
+    }
+}
+
+impl<Registry> Clone for LocalExecutor<Registry> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+/// An owned handle to a task spawned with [`LocalExecutor::spawn_local`],
+/// which can be awaited to get the task's output.
+pub struct JoinHandle<T> {
+    state: Rc<RefCell<JoinState<T>>>,
+}
+
+enum JoinState<T> {
+    Pending(Option<Waker>),
+    Ready(T),
+    Taken,
+}
+
+impl<T> JoinHandle<T> {
+    pub fn is_finished(&self) -> bool {
+        !matches!(*self.state.borrow(), JoinState::Pending(_))
+    }
+}
+
+impl<T> Future for JoinHandle<T> {
+    type Output = T;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut state = self.state.borrow_mut();
+        match std::mem::replace(&mut *state, JoinState::Taken) {
+            JoinState::Pending(_) => {
+                *state = JoinState::Pending(Some(cx.waker().clone()));
+                Poll::Pending
+            }
+            JoinState::Ready(output) => Poll::Ready(output),
+            JoinState::Taken => panic!("JoinHandle polled after completion"),
+        }
+    }
+}
+
+#[derive(Default)]
+struct ReadyQueue(Mutex<VecDeque<usize>>);
+
+impl ReadyQueue {
+    fn push(&self, id: usize) {
+        self.0.lock().unwrap().push_back(id);
+    }
+
+    fn take(&self) -> VecDeque<usize> {
+        std::mem::take(&mut self.0.lock().unwrap())
+    }
+
+    fn is_empty(&self) -> bool {
+        self.0.lock().unwrap().is_empty()
+    }
+}
+
+struct TaskWaker {
+    id: usize,
+    scheduled: AtomicBool,
+    ready: Arc<ReadyQueue>,
+}
+
+impl Wake for TaskWaker {
+    fn wake(self: Arc<Self>) {
+        self.wake_by_ref()
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        if !self.scheduled.swap(true, Ordering::SeqCst) {
+            self.ready.push(self.id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::Cell, future::pending, rc::Rc, time::Duration};
+
+    use crate::{
+        poll::{Poller, Stalled},
+        testing::{self, MockPollable},
+        time,
+    };
+
+    use super::LocalExecutor;
+
+    fn executor() -> LocalExecutor<Poller<MockPollable>> {
+        LocalExecutor::new(Poller::default())
+    }
+
+    #[test]
+    fn join_handle_yields_task_output() {
+        let executor = executor();
+        let sleep = time::sleep(executor.registry().clone(), Duration::from_secs(1));
+        let handle = executor.spawn_local(async move {
+            sleep.await;
+            5
+        });
+        assert!(!handle.is_finished());
+        assert_eq!(executor.run_until(handle).unwrap(), 5);
+    }
+
+    #[test]
+    fn detached_task_keeps_running() {
+        let executor = executor();
+        let done = Rc::new(Cell::new(false));
+        drop(executor.spawn_local({
+            let sleep = time::sleep(executor.registry().clone(), Duration::from_secs(1));
+            let done = done.clone();
+            async move {
+                sleep.await;
+                done.set(true);
+            }
+        }));
+        executor
+            .run_until(time::sleep(
+                executor.registry().clone(),
+                Duration::from_secs(2),
+            ))
+            .unwrap();
+        assert!(done.get());
+    }
+
+    #[test]
+    fn run_until_returns_while_tasks_are_pending() {
+        let executor = executor();
+        let start = testing::now();
+        let handle = executor.spawn_local(time::sleep(
+            executor.registry().clone(),
+            Duration::from_secs(10),
+        ));
+        executor
+            .run_until(time::sleep(
+                executor.registry().clone(),
+                Duration::from_secs(1),
+            ))
+            .unwrap();
+        assert_eq!(testing::now() - start, 1_000_000_000);
+        assert!(!handle.is_finished());
+
+        executor.run_until(handle).unwrap();
+        assert_eq!(testing::now() - start, 10_000_000_000);
+    }
+
+    #[test]
+    fn reports_stall() {
+        let executor = executor();
+        assert!(matches!(executor.run_until(pending::<()>()), Err(Stalled)));
+
+        let handle = executor.spawn_local(pending::<()>());
+        assert!(matches!(executor.run_until(handle), Err(Stalled)));
+    }
+}
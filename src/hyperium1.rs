@@ -1,12 +1,22 @@
+mod client;
 mod incoming;
 mod outgoing;
 mod send;
 mod service;
 
+pub use client::{ClientFuture, WasiClient};
 pub use incoming::{incoming_request, incoming_response};
-pub use outgoing::{outgoing_request, outgoing_response, Hyperium1OutgoingBodyCopier};
-pub use send::send_request;
-pub use service::handle_service_call;
+pub use outgoing::{
+    outgoing_request, outgoing_request_with_policy, outgoing_response,
+    outgoing_response_with_policy, Hyperium1OutgoingBodyCopier,
+};
+pub use send::{
+    send, send_request, send_request_timeout, send_request_with_deadline, RequestFuture,
+    ResponseFuture, SendFuture, UploadFuture,
+};
+pub use service::{handle_service_call, handle_service_call_with_deadline};
+
+pub(crate) use send::{start, IncomingResponseBody, WasiRequest};
 
 use crate::wasi::{FieldEntries, Method, Scheme};
 
@@ -83,7 +93,7 @@ impl TryFrom<FieldEntries> for http1::HeaderMap {
 
 impl From<http1::HeaderMap> for FieldEntries {
     fn from(map: http1::HeaderMap) -> Self {
-        map.into()
+        (&map).into()
     }
 }
 
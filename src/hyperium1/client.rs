@@ -0,0 +1,150 @@
+use std::{
+    fmt,
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use crate::{
+    poll::PollableRegistry,
+    wasi::traits::{
+        WasiFutureIncomingResponse, WasiHttpBindings, WasiIncomingBody, WasiOutgoingBody,
+        WasiOutgoingHandler, WasiOutgoingRequest, WasiOutputStream,
+    },
+    Error, IncomingHttpBody,
+};
+
+use super::send::{
+    start, IncomingResponseBody, RequestFuture, ResponseFuture, UploadFuture, WasiRequest,
+};
+
+/// An HTTP client which sends requests through `wasi:http/outgoing-handler`
+/// as a [`tower_service::Service`], so it can be wrapped in tower layers. The
+/// WASI request type is inferred from the registry's pollable type.
+///
+/// Each call completes once the request body has been uploaded and the
+/// response head has arrived; use [`send`](super::send) to stream both at
+/// once. A call's future is `Send` only if the registry, the request body and
+/// the WASI bindings are, so middleware which requires `Send` futures, such
+/// as tower's `Buffer`, can't wrap a client using a [`LocalPoller`] or a
+/// `!Send` body.
+///
+/// [`LocalPoller`]: crate::poll::LocalPoller
+#[derive(Clone)]
+pub struct WasiClient<Registry> {
+    registry: Registry,
+}
+
+impl<Registry: PollableRegistry> WasiClient<Registry> {
+    pub fn new(registry: Registry) -> Self {
+        Self { registry }
+    }
+
+    pub fn registry(&self) -> &Registry {
+        &self.registry
+    }
+}
+
+impl<Registry> fmt::Debug for WasiClient<Registry> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WasiClient").finish_non_exhaustive()
+    }
+}
+
+impl<HttpBody, Registry> tower_service::Service<http1::Request<HttpBody>> for WasiClient<Registry>
+where
+    HttpBody: http_body1::Body + Unpin,
+    HttpBody::Data: Unpin,
+    anyhow::Error: From<HttpBody::Error>,
+    Registry: PollableRegistry,
+    Registry::Pollable: WasiHttpBindings,
+    <<WasiRequest<Registry> as WasiOutgoingRequest>::OutgoingBody as WasiOutgoingBody>::OutputStream:
+        WasiOutputStream<Pollable = Registry::Pollable>,
+    <WasiRequest<Registry> as WasiOutgoingHandler>::FutureIncomingResponse:
+        WasiFutureIncomingResponse<Pollable = Registry::Pollable>,
+    IncomingResponseBody<WasiRequest<Registry>>: WasiIncomingBody<Pollable = Registry::Pollable>,
+{
+    type Response =
+        http1::Response<IncomingHttpBody<IncomingResponseBody<WasiRequest<Registry>>, Registry>>;
+    type Error = Error;
+    type Future = ClientFuture<
+        ResponseFuture<<WasiRequest<Registry> as WasiOutgoingHandler>::FutureIncomingResponse, Registry>,
+        UploadFuture<HttpBody, <WasiRequest<Registry> as WasiOutgoingRequest>::OutgoingBody, Registry>,
+    >;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: http1::Request<HttpBody>) -> Self::Future {
+        let state = start::<WasiRequest<Registry>, _, _>(request, self.registry.clone())
+            .map(|(response, upload)| RequestFuture::new(response, upload))
+            .map_err(Some);
+        ClientFuture { state }
+    }
+}
+
+/// The future returned by [`WasiClient`]'s `call`.
+pub struct ClientFuture<Response: Future, Upload> {
+    // Err if the request failed to start
+    state: Result<RequestFuture<Response, Upload>, Option<Error>>,
+}
+
+impl<Response, Upload, T> Future for ClientFuture<Response, Upload>
+where
+    Response: Future<Output = Result<T, Error>> + Unpin,
+    Upload: Future<Output = Result<(), Error>> + Unpin,
+    T: Unpin,
+{
+    type Output = Result<T, Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        match &mut self.get_mut().state {
+            Ok(sending) => Pin::new(sending).poll(cx),
+            Err(err) => Poll::Ready(Err(err.take().expect("polled after completion"))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+    use http_body_util::{BodyExt, Full};
+    use tower_service::Service;
+
+    use crate::{
+        poll::{PollableRegistry, Poller},
+        testing::{
+            MockFutureIncomingResponse, MockIncomingBody, MockIncomingResponse,
+            MockOutgoingHandler, MockPollable,
+        },
+        wasi::traits::WasiOutgoingRequest,
+    };
+
+    use super::WasiClient;
+
+    #[test]
+    fn calls_through_service() {
+        let _handler = MockOutgoingHandler::install(|request| {
+            assert_eq!(request.path_with_query().as_deref(), Some("/hello"));
+            let body = MockIncomingBody::with_data("hi");
+            let response = MockIncomingResponse::new(200, body);
+            Ok(MockFutureIncomingResponse::ready(Ok(response)))
+        });
+
+        let registry = Poller::<MockPollable>::default();
+        let mut client = WasiClient::new(registry.clone());
+        let request = http1::Request::get("http://example.com/hello")
+            .body(Full::new(Bytes::new()))
+            .unwrap();
+        let body = registry
+            .block_on(async {
+                let response = client.call(request).await?;
+                assert_eq!(response.status(), 200);
+                response.into_body().collect().await
+            })
+            .unwrap()
+            .unwrap();
+        assert_eq!(body.to_bytes(), "hi");
+    }
+}
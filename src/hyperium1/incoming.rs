@@ -99,3 +99,60 @@ where
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use http_body_util::BodyExt;
+
+    use crate::{
+        poll::{PollableRegistry, Poller},
+        testing::{
+            self, MockFutureTrailers, MockIncomingBody, MockInputStream, MockIoError, MockPollable,
+        },
+        wasi::ErrorCode,
+        Error, IncomingHttpBody,
+    };
+
+    #[test]
+    fn collects_body_and_trailers() {
+        let registry = Poller::<MockPollable>::default();
+        let stream = MockInputStream::new();
+        let trailers = MockFutureTrailers::new();
+        let body = MockIncomingBody::new(stream.clone(), trailers.clone());
+        let body = IncomingHttpBody::new(body, registry.clone()).unwrap();
+
+        stream.push("hello ");
+        testing::schedule(Duration::from_millis(1), move || {
+            stream.push("world");
+            stream.close();
+        });
+        testing::schedule(Duration::from_millis(2), move || {
+            trailers.resolve(Ok(Some(vec![("x-sum".into(), b"abc".to_vec())].into())))
+        });
+
+        let collected = registry.block_on(body.collect()).unwrap().unwrap();
+        assert_eq!(collected.trailers().unwrap()["x-sum"], "abc");
+        assert_eq!(collected.to_bytes(), "hello world");
+    }
+
+    #[test]
+    fn read_error_fails_body() {
+        let registry = Poller::<MockPollable>::default();
+        let stream = MockInputStream::new();
+        stream.push("partial");
+        stream.fail(MockIoError::http(ErrorCode::HttpProtocolError));
+        let body = MockIncomingBody::new(stream, MockFutureTrailers::new());
+        let body = IncomingHttpBody::new(body, registry.clone()).unwrap();
+
+        let err = registry.block_on(body.collect()).unwrap().unwrap_err();
+        assert!(matches!(
+            err,
+            Error::WasiStreamOperationFailed {
+                error_code: Some(ErrorCode::HttpProtocolError),
+                ..
+            }
+        ));
+    }
+}
@@ -10,11 +10,17 @@ use crate::{
     poll::PollableRegistry,
     wasi::{
         traits::{WasiOutgoingBody, WasiOutgoingRequest, WasiOutgoingResponse, WasiOutputStream},
-        OutgoingBody, OutgoingRequest, OutgoingResponse,
+        FieldEntries, ForbiddenHeaderPolicy, OutgoingBody, OutgoingRequest, OutgoingResponse,
     },
     Error,
 };
 
+/// Converts the head of `request`. Any headers in
+/// [`FORBIDDEN_HEADERS`](crate::wasi::FORBIDDEN_HEADERS) are handled with the
+/// [`ForbiddenHeaderPolicy`] in its extensions, which defaults to failing.
+///
+/// That list is only a client-side pre-check made before the headers are
+/// passed to the host, which remains authoritative and may reject others.
 pub fn outgoing_request<B, Request, Registry>(
     request: &http1::Request<B>,
     registry: Registry,
@@ -25,7 +31,24 @@ where
         WasiOutputStream<Pollable = Registry::Pollable>,
     Registry: PollableRegistry,
 {
-    let mut req = OutgoingRequest::from_headers(&request.headers().into(), registry)?;
+    let policy = request.extensions().get().copied().unwrap_or_default();
+    outgoing_request_with_policy(request, policy, registry)
+}
+
+pub fn outgoing_request_with_policy<B, Request, Registry>(
+    request: &http1::Request<B>,
+    policy: ForbiddenHeaderPolicy,
+    registry: Registry,
+) -> Result<OutgoingRequest<Request, Registry>, Error>
+where
+    Request: WasiOutgoingRequest,
+    <Request::OutgoingBody as WasiOutgoingBody>::OutputStream:
+        WasiOutputStream<Pollable = Registry::Pollable>,
+    Registry: PollableRegistry,
+{
+    let mut headers: FieldEntries = request.headers().into();
+    let host = headers.sanitize(policy)?;
+    let mut req = OutgoingRequest::from_headers(&headers, registry)?;
     req.set_method(request.method().into())?;
     if let Some(path_with_query) = request.uri().path_and_query() {
         req.set_path_with_query(Some(path_with_query.as_str()))?;
@@ -35,11 +58,21 @@ where
     }
     if let Some(authority) = request.uri().authority() {
         req.set_authority(Some(authority.as_str()))?;
+    } else if let Some(host) = host {
+        let host = std::str::from_utf8(&host)
+            .map_err(|_| Error::WasiInvalidValue("invalid host header"))?;
+        req.set_authority(Some(host))?;
     }
 
     Ok(req)
 }
 
+/// Converts the head of `resp`. Any headers in
+/// [`FORBIDDEN_HEADERS`](crate::wasi::FORBIDDEN_HEADERS) are handled with the
+/// [`ForbiddenHeaderPolicy`] in its extensions, which defaults to failing.
+///
+/// That list is only a client-side pre-check made before the headers are
+/// passed to the host, which remains authoritative and may reject others.
 pub fn outgoing_response<B, Response, Registry>(
     resp: &http1::Response<B>,
     registry: Registry,
@@ -50,7 +83,26 @@ where
         WasiOutputStream<Pollable = Registry::Pollable>,
     Registry: PollableRegistry,
 {
-    let mut outgoing = OutgoingResponse::from_headers(&resp.headers().into(), registry)?;
+    let policy = resp.extensions().get().copied().unwrap_or_default();
+    outgoing_response_with_policy(resp, policy, registry)
+}
+
+/// Responses have no authority, so [`ForbiddenHeaderPolicy::MapHostToAuthority`]
+/// strips `Host` like any other forbidden header.
+pub fn outgoing_response_with_policy<B, Response, Registry>(
+    resp: &http1::Response<B>,
+    policy: ForbiddenHeaderPolicy,
+    registry: Registry,
+) -> Result<OutgoingResponse<Response, Registry>, Error>
+where
+    Response: WasiOutgoingResponse,
+    <Response::OutgoingBody as WasiOutgoingBody>::OutputStream:
+        WasiOutputStream<Pollable = Registry::Pollable>,
+    Registry: PollableRegistry,
+{
+    let mut headers: FieldEntries = resp.headers().into();
+    headers.sanitize(policy)?;
+    let mut outgoing = OutgoingResponse::from_headers(&headers, registry)?;
     outgoing.set_status_code(resp.status().as_u16())?;
     Ok(outgoing)
 }
@@ -143,3 +195,76 @@ where
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+    use http_body_util::Full;
+
+    use crate::{
+        outgoing::OutgoingBodyCopier,
+        poll::{PollableRegistry, Poller},
+        testing::{
+            MockIoError, MockOutgoingBody, MockOutgoingRequest, MockOutputStream, MockPollable,
+        },
+        wasi::{FieldsError, FieldsErrorKind, OutgoingBody},
+        Error,
+    };
+
+    use super::{outgoing_request, Hyperium1OutgoingBodyCopier};
+
+    #[test]
+    fn copies_body_in_permitted_chunks() {
+        let registry = Poller::<MockPollable>::default();
+        let stream = MockOutputStream::with_permits([3, 0, 2]);
+        let wasi_body = MockOutgoingBody::new(stream);
+        let dest = OutgoingBody::new(wasi_body.clone(), registry.clone()).unwrap();
+        let src = Full::new(Bytes::from_static(b"hello world"));
+
+        let copier = Hyperium1OutgoingBodyCopier::new(src, dest).unwrap();
+        registry.block_on(copier.copy_all()).unwrap().unwrap();
+        assert_eq!(wasi_body.written(), b"hello world");
+        assert!(wasi_body.is_finished());
+        assert_eq!(wasi_body.trailers(), None);
+    }
+
+    #[test]
+    fn write_error_fails_copy() {
+        let registry = Poller::<MockPollable>::default();
+        let stream = MockOutputStream::new();
+        stream.fail(MockIoError::new("broken pipe"));
+        let wasi_body = MockOutgoingBody::new(stream);
+        let dest = OutgoingBody::new(wasi_body.clone(), registry.clone()).unwrap();
+        let src = Full::new(Bytes::from_static(b"hello"));
+
+        let copier = Hyperium1OutgoingBodyCopier::new(src, dest).unwrap();
+        let err = registry.block_on(copier.copy_all()).unwrap().unwrap_err();
+        assert!(matches!(
+            err,
+            Error::WasiStreamOperationFailed {
+                error_code: None,
+                ..
+            }
+        ));
+        assert!(!wasi_body.is_finished());
+    }
+
+    #[test]
+    fn rejects_forbidden_header_by_default() {
+        let request = http1::Request::get("http://example.com/")
+            .header("connection", "keep-alive")
+            .body(())
+            .unwrap();
+        let res = outgoing_request::<_, MockOutgoingRequest, _>(
+            &request,
+            Poller::<MockPollable>::default(),
+        );
+        assert!(matches!(
+            res,
+            Err(Error::WasiFieldsError(FieldsError {
+                name,
+                kind: FieldsErrorKind::Forbidden,
+            })) if name == "connection"
+        ));
+    }
+}
@@ -1,21 +1,37 @@
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{ready, Context, Poll},
+    time::Duration,
+};
+
 use crate::{
+    executor::{JoinHandle, LocalExecutor},
     hyperium1::{incoming_response, Hyperium1OutgoingBodyCopier},
-    outgoing::OutgoingBodyCopier,
-    poll::PollableRegistry,
+    outgoing::{CopyAllFuture, OutgoingBodyCopier},
+    poll::{BlockOnError, PollableRegistry},
+    time::{self, Elapsed, Instant},
     wasi::{
         traits::{
-            WasiFutureIncomingResponse, WasiIncomingBody, WasiIncomingResponse, WasiOutgoingBody,
-            WasiOutgoingHandler, WasiOutputStream,
+            WasiFutureIncomingResponse, WasiHttpBindings, WasiIncomingBody, WasiIncomingResponse,
+            WasiMonotonicClock, WasiOutgoingBody, WasiOutgoingHandler, WasiOutputStream,
         },
-        OutgoingRequest,
+        FutureIncomingResponse, OutgoingRequest, RequestOptions,
     },
     Error, IncomingHttpBody,
 };
 
 use super::outgoing_request;
 
-type IncomingResponseBody<Request> = <<<Request as WasiOutgoingHandler>::FutureIncomingResponse as WasiFutureIncomingResponse>::IncomingResponse as WasiIncomingResponse>::IncomingBody;
+/// The outgoing request type of the bindings `Registry` polls.
+pub(crate) type WasiRequest<Registry> =
+    <<Registry as PollableRegistry>::Pollable as WasiHttpBindings>::OutgoingRequest;
 
+pub(crate) type IncomingResponseBody<Request> = <<<Request as WasiOutgoingHandler>::FutureIncomingResponse as WasiFutureIncomingResponse>::IncomingResponse as WasiIncomingResponse>::IncomingBody;
+
+/// Sends `request`, blocking until its body has been uploaded and the
+/// response head has arrived. Transport timeouts are taken from a
+/// [`RequestOptions`] in the request's extensions, if present.
 pub fn send_request<WasiRequest, HttpBody, Registry>(
     request: http1::Request<HttpBody>,
     registry: Registry,
@@ -32,10 +48,517 @@ where
         WasiIncomingBody<Pollable = Registry::Pollable>,
     Registry: PollableRegistry,
 {
+    let (response, upload) = start::<WasiRequest, _, _>(request, registry.clone())?;
+    registry.block_on(RequestFuture::new(response, upload))?
+}
+
+/// Like [`send_request`], but fails with [`Error::Elapsed`] if the request
+/// body upload and response together take longer than `timeout`.
+pub fn send_request_timeout<WasiRequest, HttpBody, Registry>(
+    request: http1::Request<HttpBody>,
+    registry: Registry,
+    timeout: Duration,
+) -> Result<http1::Response<IncomingHttpBody<IncomingResponseBody<WasiRequest>, Registry>>, Error>
+where
+    HttpBody: http_body1::Body + Unpin,
+    HttpBody::Data: Unpin,
+    anyhow::Error: From<HttpBody::Error>,
+    WasiRequest: WasiOutgoingHandler,
+    <WasiRequest::OutgoingBody as WasiOutgoingBody>::OutputStream:
+        WasiOutputStream<Pollable = Registry::Pollable>,
+    WasiRequest::FutureIncomingResponse: WasiFutureIncomingResponse<Pollable = Registry::Pollable>,
+    <<WasiRequest::FutureIncomingResponse as WasiFutureIncomingResponse>::IncomingResponse as WasiIncomingResponse>::IncomingBody:
+        WasiIncomingBody<Pollable = Registry::Pollable>,
+    Registry: PollableRegistry,
+    Registry::Pollable: WasiMonotonicClock,
+{
+    let deadline = time::now::<Registry>().saturating_add(time::duration_nanos(timeout));
+    match send_request_with_deadline::<WasiRequest, _, _>(request, registry, deadline) {
+        Err(Error::BlockOnError(BlockOnError::TimedOut)) => Err(Elapsed.into()),
+        res => res,
+    }
+}
+
+/// Like [`send_request`], but fails with [`Error::BlockOnError`] if the
+/// request body upload and response together do not complete before the
+/// monotonic clock reaches `deadline`.
+pub fn send_request_with_deadline<WasiRequest, HttpBody, Registry>(
+    request: http1::Request<HttpBody>,
+    registry: Registry,
+    deadline: Instant,
+) -> Result<http1::Response<IncomingHttpBody<IncomingResponseBody<WasiRequest>, Registry>>, Error>
+where
+    HttpBody: http_body1::Body + Unpin,
+    HttpBody::Data: Unpin,
+    anyhow::Error: From<HttpBody::Error>,
+    WasiRequest: WasiOutgoingHandler,
+    <WasiRequest::OutgoingBody as WasiOutgoingBody>::OutputStream:
+        WasiOutputStream<Pollable = Registry::Pollable>,
+    WasiRequest::FutureIncomingResponse: WasiFutureIncomingResponse<Pollable = Registry::Pollable>,
+    <<WasiRequest::FutureIncomingResponse as WasiFutureIncomingResponse>::IncomingResponse as WasiIncomingResponse>::IncomingBody:
+        WasiIncomingBody<Pollable = Registry::Pollable>,
+    Registry: PollableRegistry,
+    Registry::Pollable: WasiMonotonicClock,
+{
+    let (response, upload) = start::<WasiRequest, _, _>(request, registry.clone())?;
+    registry.block_on_with_deadline(deadline, RequestFuture::new(response, upload))?
+}
+
+/// Starts sending `request`, returning a future which resolves to the
+/// response head while the request body is uploaded by a task spawned on
+/// `executor`. The upload keeps going after the response arrives for as long
+/// as the executor runs, so both bodies can be streamed at once. Upload
+/// failures before the response head arrives are returned by the future;
+/// after that, the host sees the request body end without being finished and
+/// fails the rest of the exchange. Transport timeouts are taken from a
+/// [`RequestOptions`] in the request's extensions, if present.
+pub fn send<WasiRequest, HttpBody, Registry>(
+    request: http1::Request<HttpBody>,
+    executor: &LocalExecutor<Registry>,
+) -> SendFuture<WasiRequest::FutureIncomingResponse, Registry>
+where
+    HttpBody: http_body1::Body + Unpin + 'static,
+    HttpBody::Data: Unpin,
+    anyhow::Error: From<HttpBody::Error>,
+    WasiRequest: WasiOutgoingHandler,
+    WasiRequest::OutgoingBody: 'static,
+    <WasiRequest::OutgoingBody as WasiOutgoingBody>::OutputStream:
+        WasiOutputStream<Pollable = Registry::Pollable>,
+    WasiRequest::FutureIncomingResponse: WasiFutureIncomingResponse<Pollable = Registry::Pollable>,
+    <<WasiRequest::FutureIncomingResponse as WasiFutureIncomingResponse>::IncomingResponse as WasiIncomingResponse>::IncomingBody:
+        WasiIncomingBody<Pollable = Registry::Pollable>,
+    Registry: PollableRegistry + 'static,
+{
+    let state = start::<WasiRequest, _, _>(request, executor.registry().clone())
+        .map(|(response, upload)| (response, Some(executor.spawn_local(upload))))
+        .map_err(Some);
+    SendFuture { state }
+}
+
+/// Starts sending `request`, returning futures for the response head and the
+/// request body upload, which must both be driven.
+#[allow(clippy::type_complexity)]
+pub(crate) fn start<WasiRequest, HttpBody, Registry>(
+    request: http1::Request<HttpBody>,
+    registry: Registry,
+) -> Result<
+    (
+        ResponseFuture<WasiRequest::FutureIncomingResponse, Registry>,
+        UploadFuture<HttpBody, WasiRequest::OutgoingBody, Registry>,
+    ),
+    Error,
+>
+where
+    HttpBody: http_body1::Body + Unpin,
+    HttpBody::Data: Unpin,
+    anyhow::Error: From<HttpBody::Error>,
+    WasiRequest: WasiOutgoingHandler,
+    <WasiRequest::OutgoingBody as WasiOutgoingBody>::OutputStream:
+        WasiOutputStream<Pollable = Registry::Pollable>,
+    WasiRequest::FutureIncomingResponse: WasiFutureIncomingResponse<Pollable = Registry::Pollable>,
+    <<WasiRequest::FutureIncomingResponse as WasiFutureIncomingResponse>::IncomingResponse as WasiIncomingResponse>::IncomingBody:
+        WasiIncomingBody<Pollable = Registry::Pollable>,
+    Registry: PollableRegistry,
+{
+    let options = request.extensions().get::<RequestOptions>().copied();
     let outgoing: OutgoingRequest<WasiRequest, _> = outgoing_request(&request, registry.clone())?;
-    let (outgoing_body, future_response) = outgoing.send(None)?.into_parts();
+    let (outgoing_body, future_response) = outgoing.send(options.as_ref())?.into_parts();
     let copier = Hyperium1OutgoingBodyCopier::new(request.into_body(), outgoing_body)?;
-    registry.block_on(copier.copy_all()).unwrap()?;
-    let incoming = registry.block_on(future_response).unwrap()?;
-    incoming_response(incoming)
+    let response = ResponseFuture {
+        inner: future_response,
+    };
+    Ok((response, copier.copy_all()))
+}
+
+/// The future returned by [`send`].
+pub struct SendFuture<FutureResponse, Registry: PollableRegistry> {
+    // Err if the request failed to start
+    #[allow(clippy::type_complexity)]
+    state: Result<
+        (
+            ResponseFuture<FutureResponse, Registry>,
+            // Until it completes or the response arrives
+            Option<JoinHandle<Result<(), Error>>>,
+        ),
+        Option<Error>,
+    >,
+}
+
+impl<FutureResponse, Registry> Future for SendFuture<FutureResponse, Registry>
+where
+    FutureResponse: WasiFutureIncomingResponse<Pollable = Registry::Pollable>,
+    <FutureResponse::IncomingResponse as WasiIncomingResponse>::IncomingBody:
+        WasiIncomingBody<Pollable = Registry::Pollable>,
+    Registry: PollableRegistry,
+{
+    type Output = <ResponseFuture<FutureResponse, Registry> as Future>::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let (response, upload) = match &mut self.get_mut().state {
+            Ok(sending) => sending,
+            Err(err) => return Poll::Ready(Err(err.take().expect("polled after completion"))),
+        };
+        if let Some(uploading) = upload {
+            if let Poll::Ready(res) = Pin::new(uploading).poll(cx) {
+                *upload = None;
+                res?;
+            }
+        }
+        Pin::new(response).poll(cx)
+    }
+}
+
+/// Uploads the body of a request.
+pub type UploadFuture<HttpBody, WasiBody, Registry> =
+    CopyAllFuture<Hyperium1OutgoingBodyCopier<HttpBody, WasiBody, Registry>>;
+
+/// Resolves to the response head of a request.
+pub struct ResponseFuture<FutureResponse, Registry: PollableRegistry> {
+    inner: FutureIncomingResponse<FutureResponse, Registry>,
+}
+
+impl<FutureResponse, Registry> Future for ResponseFuture<FutureResponse, Registry>
+where
+    FutureResponse: WasiFutureIncomingResponse<Pollable = Registry::Pollable>,
+    <FutureResponse::IncomingResponse as WasiIncomingResponse>::IncomingBody:
+        WasiIncomingBody<Pollable = Registry::Pollable>,
+    Registry: PollableRegistry,
+{
+    type Output = Result<
+        http1::Response<
+            IncomingHttpBody<
+                <FutureResponse::IncomingResponse as WasiIncomingResponse>::IncomingBody,
+                Registry,
+            >,
+        >,
+        Error,
+    >;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let incoming = ready!(Pin::new(&mut self.inner).poll(cx))?;
+        Poll::Ready(incoming_response(incoming))
+    }
+}
+
+/// Drives the response head and body upload of a request together,
+/// resolving to the response once the upload has completed too. Fails as
+/// soon as either does.
+pub struct RequestFuture<Response: Future, Upload> {
+    response: Response,
+    received: Option<Response::Output>,
+    upload: Option<Upload>,
+}
+
+impl<Response: Future, Upload> RequestFuture<Response, Upload> {
+    pub(crate) fn new(response: Response, upload: Upload) -> Self {
+        Self {
+            response,
+            received: None,
+            upload: Some(upload),
+        }
+    }
+}
+
+impl<Response, Upload, T> Future for RequestFuture<Response, Upload>
+where
+    Response: Future<Output = Result<T, Error>> + Unpin,
+    Upload: Future<Output = Result<(), Error>> + Unpin,
+    T: Unpin,
+{
+    type Output = Result<T, Error>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = &mut *self;
+        if let Some(upload) = &mut this.upload {
+            if let Poll::Ready(res) = Pin::new(upload).poll(cx) {
+                res?;
+                this.upload = None;
+            }
+        }
+        if this.received.is_none() {
+            if let Poll::Ready(res) = Pin::new(&mut this.response).poll(cx) {
+                this.received = Some(Ok(res?));
+            }
+        }
+        if this.upload.is_none() {
+            if let Some(res) = this.received.take() {
+                return Poll::Ready(res);
+            }
+        }
+        Poll::Pending
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, rc::Rc, time::Duration};
+
+    use bytes::Bytes;
+    use http_body1::Frame;
+    use http_body_util::{BodyExt, Full, StreamBody};
+
+    use crate::{
+        executor::LocalExecutor,
+        poll::{PollableRegistry, Poller},
+        testing::{
+            self, MockFutureIncomingResponse, MockFutureTrailers, MockIncomingBody,
+            MockIncomingResponse, MockInputStream, MockOutgoingHandler, MockOutgoingRequest,
+            MockPollable,
+        },
+        wasi::{
+            traits::{WasiFields, WasiOutgoingRequest},
+            ErrorCode, ForbiddenHeaderPolicy, Method, RequestOptions, Scheme,
+        },
+        Error,
+    };
+
+    use super::{send, send_request, send_request_timeout};
+
+    #[test]
+    fn sends_request_and_receives_response() {
+        let _handler = MockOutgoingHandler::install(|request| {
+            assert_eq!(request.method(), Method::Post);
+            assert_eq!(request.scheme(), Some(Scheme::Https));
+            assert_eq!(request.authority().as_deref(), Some("example.com"));
+            assert_eq!(request.path_with_query().as_deref(), Some("/echo?x=1"));
+            assert_eq!(
+                request.headers().entries(),
+                [("x-test".into(), b"yes".to_vec())]
+            );
+
+            // Echo the body once it has been written
+            let body = request.outgoing_body();
+            let response = MockFutureIncomingResponse::new();
+            testing::schedule(Duration::ZERO, {
+                let response = response.clone();
+                move || {
+                    assert!(body.is_finished());
+                    let body = MockIncomingBody::with_data(body.written());
+                    response.resolve(Ok(MockIncomingResponse::new(201, body)));
+                }
+            });
+            Ok(response)
+        });
+
+        let registry = Poller::<MockPollable>::default();
+        let request = http1::Request::post("https://example.com/echo?x=1")
+            .header("x-test", "yes")
+            .body(Full::new(Bytes::from_static(b"ping")))
+            .unwrap();
+        let response =
+            send_request::<MockOutgoingRequest, _, _>(request, registry.clone()).unwrap();
+        assert_eq!(response.status(), 201);
+        let body = registry.block_on(response.into_body().collect()).unwrap();
+        assert_eq!(body.unwrap().to_bytes(), "ping");
+    }
+
+    #[test]
+    fn passes_request_options_from_extensions() {
+        let _handler = MockOutgoingHandler::install(|request| {
+            let options = request.options().unwrap();
+            assert_eq!(options.connect_timeout(), Some(Duration::from_secs(1)));
+            assert_eq!(options.first_byte_timeout(), None);
+            assert_eq!(
+                options.between_bytes_timeout(),
+                Some(Duration::from_millis(5))
+            );
+            let response = MockIncomingResponse::new(204, MockIncomingBody::with_data(""));
+            Ok(MockFutureIncomingResponse::ready(Ok(response)))
+        });
+
+        let registry = Poller::<MockPollable>::default();
+        let mut request = http1::Request::get("http://example.com/")
+            .body(Full::new(Bytes::new()))
+            .unwrap();
+        request.extensions_mut().insert(RequestOptions {
+            connect_timeout: Some(Duration::from_secs(1)),
+            between_bytes_timeout: Some(Duration::from_millis(5)),
+            ..Default::default()
+        });
+        let response = send_request::<MockOutgoingRequest, _, _>(request, registry).unwrap();
+        assert_eq!(response.status(), 204);
+    }
+
+    #[test]
+    fn strips_forbidden_headers_when_asked() {
+        let _handler = MockOutgoingHandler::install(|request| {
+            assert_eq!(request.authority().as_deref(), Some("example.com"));
+            assert_eq!(
+                request.headers().entries(),
+                [("accept".into(), b"*/*".to_vec())]
+            );
+            let response = MockIncomingResponse::new(204, MockIncomingBody::with_data(""));
+            Ok(MockFutureIncomingResponse::ready(Ok(response)))
+        });
+
+        let registry = Poller::<MockPollable>::default();
+        let mut request = http1::Request::get("http://example.com/")
+            .header("host", "example.com")
+            .header("connection", "close")
+            .header("accept", "*/*")
+            .body(Full::new(Bytes::new()))
+            .unwrap();
+        request
+            .extensions_mut()
+            .insert(ForbiddenHeaderPolicy::Strip);
+        let response = send_request::<MockOutgoingRequest, _, _>(request, registry).unwrap();
+        assert_eq!(response.status(), 204);
+    }
+
+    #[test]
+    fn maps_host_header_to_authority() {
+        let _handler = MockOutgoingHandler::install(|request| {
+            assert_eq!(request.authority().as_deref(), Some("example.com:8080"));
+            assert_eq!(request.path_with_query().as_deref(), Some("/status"));
+            assert!(request.headers().entries().is_empty());
+            let response = MockIncomingResponse::new(204, MockIncomingBody::with_data(""));
+            Ok(MockFutureIncomingResponse::ready(Ok(response)))
+        });
+
+        let registry = Poller::<MockPollable>::default();
+        let mut request = http1::Request::get("/status")
+            .header("host", "example.com:8080")
+            .body(Full::new(Bytes::new()))
+            .unwrap();
+        request
+            .extensions_mut()
+            .insert(ForbiddenHeaderPolicy::MapHostToAuthority);
+        let response = send_request::<MockOutgoingRequest, _, _>(request, registry).unwrap();
+        assert_eq!(response.status(), 204);
+    }
+
+    #[test]
+    fn surfaces_structured_error_code() {
+        let _handler = MockOutgoingHandler::install(|_| {
+            Ok(MockFutureIncomingResponse::ready(Err(
+                ErrorCode::DnsTimeout,
+            )))
+        });
+
+        let registry = Poller::<MockPollable>::default();
+        let request = http1::Request::get("http://example.com/")
+            .body(Full::new(Bytes::new()))
+            .unwrap();
+        let res = send_request::<MockOutgoingRequest, _, _>(request, registry);
+        assert!(matches!(
+            res,
+            Err(Error::WasiErrorCode(ErrorCode::DnsTimeout))
+        ));
+    }
+
+    #[test]
+    fn times_out_waiting_for_response() {
+        let _handler = MockOutgoingHandler::install(|_| Ok(MockFutureIncomingResponse::new()));
+
+        let registry = Poller::<MockPollable>::default();
+        let request = http1::Request::get("http://example.com/")
+            .body(Full::new(Bytes::new()))
+            .unwrap();
+        let res = send_request_timeout::<MockOutgoingRequest, _, _>(
+            request,
+            registry,
+            Duration::from_secs(1),
+        );
+        assert!(matches!(res, Err(Error::Elapsed(_))));
+    }
+
+    #[test]
+    fn timeout_covers_upload_and_response_together() {
+        let _handler = MockOutgoingHandler::install(|request| {
+            // Each phase takes 600ms, which only exceeds the timeout together
+            let stream = request.outgoing_body().stream();
+            stream.set_refill(None);
+            testing::schedule(Duration::from_millis(600), move || stream.grant(4));
+            let response = MockFutureIncomingResponse::new();
+            testing::schedule(Duration::from_millis(1200), {
+                let response = response.clone();
+                move || {
+                    let body = MockIncomingBody::with_data("");
+                    response.resolve(Ok(MockIncomingResponse::new(204, body)));
+                }
+            });
+            Ok(response)
+        });
+
+        let registry = Poller::<MockPollable>::default();
+        let request = http1::Request::post("http://example.com/")
+            .body(Full::new(Bytes::from_static(b"ping")))
+            .unwrap();
+        let res = send_request_timeout::<MockOutgoingRequest, _, _>(
+            request,
+            registry,
+            Duration::from_secs(1),
+        );
+        assert!(matches!(res, Err(Error::Elapsed(_))));
+    }
+
+    #[test]
+    fn send_resolves_before_upload_completes() {
+        let upload = Rc::new(RefCell::new(None));
+        let _handler = MockOutgoingHandler::install({
+            let upload = upload.clone();
+            move |request| {
+                // Hold the upload back until after the whole response arrives
+                let body = request.outgoing_body();
+                body.stream().set_refill(None);
+                testing::schedule(Duration::from_secs(2), {
+                    let stream = body.stream();
+                    move || stream.grant(4)
+                });
+                *upload.borrow_mut() = Some(body);
+
+                let input = MockInputStream::new();
+                testing::schedule(Duration::from_secs(1), {
+                    let input = input.clone();
+                    move || {
+                        input.push("pong");
+                        input.close();
+                    }
+                });
+                let body = MockIncomingBody::new(input, MockFutureTrailers::ready(None));
+                let response = MockIncomingResponse::new(200, body);
+                Ok(MockFutureIncomingResponse::ready(Ok(response)))
+            }
+        });
+
+        let executor = LocalExecutor::new(Poller::<MockPollable>::default());
+        let request = http1::Request::post("http://example.com/")
+            .body(Full::new(Bytes::from_static(b"ping")))
+            .unwrap();
+        let response = send::<MockOutgoingRequest, _, _>(request, &executor);
+        let upload = upload.take().unwrap();
+
+        executor
+            .run_until(async {
+                let response = response.await.unwrap();
+                let body = response.into_body().collect().await.unwrap();
+                assert_eq!(body.to_bytes(), "pong");
+                assert!(upload.written().is_empty());
+            })
+            .unwrap();
+        // The upload carries on in the background
+        let registry = executor.registry().clone();
+        executor
+            .run_until(crate::time::sleep(registry, Duration::from_secs(2)))
+            .unwrap();
+        assert_eq!(upload.written(), b"ping");
+        assert!(upload.is_finished());
+    }
+
+    #[test]
+    fn send_fails_when_upload_fails_before_response() {
+        let _handler = MockOutgoingHandler::install(|_| Ok(MockFutureIncomingResponse::new()));
+
+        let executor = LocalExecutor::new(Poller::<MockPollable>::default());
+        let body = StreamBody::new(futures_util::stream::iter([Err::<Frame<Bytes>, _>(
+            anyhow::anyhow!("boom"),
+        )]));
+        let request = http1::Request::post("http://example.com/")
+            .body(body)
+            .unwrap();
+        let res = executor
+            .run_until(send::<MockOutgoingRequest, _, _>(request, &executor))
+            .unwrap();
+        assert!(matches!(res, Err(Error::BodyError(err)) if err.to_string() == "boom"));
+    }
 }
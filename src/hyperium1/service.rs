@@ -1,13 +1,14 @@
-use std::{convert::Infallible, task::Context};
+use std::{convert::Infallible, future::poll_fn, task::Context};
 
 use crate::{
     hyperium1::{incoming_request, outgoing_response},
     outgoing::OutgoingBodyCopier,
     poll::{noop_waker, PollableRegistry},
+    time::Instant,
     wasi::{
         traits::{
-            WasiIncomingBody, WasiIncomingRequest, WasiOutgoingBody, WasiOutgoingResponse,
-            WasiOutputStream, WasiResponseOutparam,
+            WasiIncomingBody, WasiIncomingRequest, WasiMonotonicClock, WasiOutgoingBody,
+            WasiOutgoingResponse, WasiOutputStream, WasiResponseOutparam,
         },
         IncomingRequest, ResponseOutparam,
     },
@@ -65,3 +66,120 @@ where
     let copier = Hyperium1OutgoingBodyCopier::new(resp.into_body(), dest)?;
     registry.block_on(copier.copy_all()).unwrap()
 }
+
+/// Like [`handle_service_call`], but fails with [`Error::BlockOnError`] if
+/// the service and the response body copy together do not complete before
+/// the monotonic clock reaches `deadline`.
+pub fn handle_service_call_with_deadline<
+    Service,
+    Request,
+    Outparam,
+    ResponseBody,
+    Registry,
+>(
+    mut service: Service,
+    request: Request,
+    response_out: Outparam,
+    registry: Registry,
+    deadline: Instant,
+) -> Result<(), Error>
+where
+    Service: tower_service::Service<
+        http1::Request<
+            IncomingHttpBody<Request::IncomingBody, Registry>,
+        >,
+        Response = http1::Response<ResponseBody>,
+        Error = Infallible,
+    >,
+    ResponseBody: http_body1::Body + Unpin,
+    ResponseBody::Data: Unpin,
+    anyhow::Error: From<ResponseBody::Error>,
+    Request: WasiIncomingRequest,
+    Request::IncomingBody: WasiIncomingBody<Pollable = Registry::Pollable>,
+    Outparam: WasiResponseOutparam,
+    <<Outparam::OutgoingResponse as WasiOutgoingResponse>::OutgoingBody as WasiOutgoingBody>::OutputStream: WasiOutputStream<Pollable = Registry::Pollable>,
+    Registry: PollableRegistry,
+    Registry::Pollable: WasiMonotonicClock,
+{
+    registry
+        .block_on_with_deadline(deadline, poll_fn(|cx| service.poll_ready(cx)))?
+        .unwrap();
+
+    let incoming = IncomingRequest::new(request, registry.clone())?;
+    let req = incoming_request(incoming)?;
+
+    let resp = registry
+        .block_on_with_deadline(deadline, service.call(req))?
+        .unwrap();
+
+    let outgoing = outgoing_response(&resp, registry.clone())?;
+    let dest = ResponseOutparam::new(response_out).set_response(outgoing);
+
+    let copier = Hyperium1OutgoingBodyCopier::new(resp.into_body(), dest)?;
+    registry.block_on_with_deadline(deadline, copier.copy_all())?
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        convert::Infallible,
+        future::{ready, Ready},
+        task::{Context, Poll},
+    };
+
+    use bytes::Bytes;
+    use http_body_util::Full;
+
+    use crate::{
+        poll::Poller,
+        testing::{MockIncomingBody, MockIncomingRequest, MockPollable, MockResponseOutparam},
+        wasi::{
+            traits::{WasiFields, WasiOutgoingResponse},
+            Method,
+        },
+    };
+
+    use super::handle_service_call;
+
+    struct Hello;
+
+    impl<B> tower_service::Service<http1::Request<B>> for Hello {
+        type Response = http1::Response<Full<Bytes>>;
+        type Error = Infallible;
+        type Future = Ready<Result<Self::Response, Infallible>>;
+
+        fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Infallible>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, req: http1::Request<B>) -> Self::Future {
+            let body = format!("hello {}", req.uri().path());
+            let resp = http1::Response::builder()
+                .status(202)
+                .header("x-method", req.method().as_str())
+                .body(Full::new(body.into()))
+                .unwrap();
+            ready(Ok(resp))
+        }
+    }
+
+    #[test]
+    fn writes_service_response_to_outparam() {
+        let registry = Poller::<MockPollable>::default();
+        let request =
+            MockIncomingRequest::new(Method::Put, Some("/world"), MockIncomingBody::with_data(""));
+        let outparam = MockResponseOutparam::new();
+
+        handle_service_call(Hello, request, outparam.clone(), registry).unwrap();
+
+        let response = outparam.take_response().unwrap().unwrap();
+        assert_eq!(response.status_code(), 202);
+        assert_eq!(
+            response.headers().entries(),
+            [("x-method".into(), b"PUT".to_vec())]
+        );
+        let body = response.outgoing_body();
+        assert_eq!(body.written(), b"hello /world");
+        assert!(body.is_finished());
+    }
+}
@@ -1,14 +1,15 @@
 use std::{
     future::Future,
+    io,
     pin::Pin,
-    task::{Context, Poll},
+    task::{ready, Context, Poll},
 };
 
 use bytes::Bytes;
 
 use crate::{
     poll::PollableRegistry,
-    wasi::{traits::WasiIncomingBody, FieldEntries, FutureTrailers, IncomingBody},
+    wasi::{traits::WasiIncomingBody, ErrorCode, FieldEntries, FutureTrailers, IncomingBody},
     Error,
 };
 
@@ -70,7 +71,7 @@ where
                     Poll::Ready(Ok(None))
                 }
                 // TODO: figure out why this is happening
-                Poll::Ready(Err(Error::WasiErrorCode(s))) if s.contains("ConnectionTerminated") => {
+                Poll::Ready(Err(Error::WasiErrorCode(ErrorCode::ConnectionTerminated))) => {
                     self.state = IncomingState::Empty;
                     Poll::Ready(Ok(None))
                 }
@@ -80,6 +81,32 @@ where
         }
     }
 
+    /// Converts the body into a stream of data chunks.
+    pub fn into_stream(self) -> IncomingBodyStream<Body, Registry> {
+        IncomingBodyStream { body: self }
+    }
+
+    /// Converts the body into an async reader of its data.
+    pub fn into_reader(self) -> IncomingBodyReader<Body, Registry> {
+        IncomingBodyReader {
+            stream: self.into_stream(),
+            chunk: Bytes::new(),
+        }
+    }
+
+    // Discards any remaining data, then polls for the trailers
+    fn poll_skip_to_trailers(
+        &mut self,
+        cx: &mut Context,
+    ) -> Poll<Result<Option<FieldEntries>, Error>> {
+        while let IncomingState::Body(_) = self.state {
+            if let Some(Err(err)) = ready!(self.poll_incoming_body(cx)) {
+                return Poll::Ready(Err(err));
+            }
+        }
+        self.poll_incoming_trailers(cx)
+    }
+
     pub(crate) fn take_body(&mut self) -> IncomingBody<Body, Registry> {
         match std::mem::replace(&mut self.state, IncomingState::Empty) {
             IncomingState::Body(body) => body,
@@ -99,3 +126,192 @@ where
         }
     }
 }
+
+/// The data of an [`IncomingHttpBody`] as a stream of chunks. Once the stream
+/// ends, the body's trailers are available from
+/// [`IncomingBodyStream::trailers`].
+pub struct IncomingBodyStream<Body, Registry>
+where
+    Body: WasiIncomingBody,
+    Registry: PollableRegistry,
+{
+    body: IncomingHttpBody<Body, Registry>,
+}
+
+impl<Body, Registry> IncomingBodyStream<Body, Registry>
+where
+    Body: WasiIncomingBody,
+    Registry: PollableRegistry<Pollable = Body::Pollable>,
+{
+    pub fn poll_next_data(&mut self, cx: &mut Context) -> Poll<Option<Result<Bytes, Error>>> {
+        match self.body.state {
+            IncomingState::Body(_) => self.body.poll_incoming_body(cx),
+            _ => Poll::Ready(None),
+        }
+    }
+
+    /// Waits for the trailers, discarding any data not yet read.
+    pub async fn trailers(mut self) -> Result<Option<FieldEntries>, Error> {
+        std::future::poll_fn(|cx| self.body.poll_skip_to_trailers(cx)).await
+    }
+
+    pub fn into_inner(self) -> IncomingHttpBody<Body, Registry> {
+        self.body
+    }
+}
+
+#[cfg(feature = "stream")]
+impl<Body, Registry> futures_core::Stream for IncomingBodyStream<Body, Registry>
+where
+    Body: WasiIncomingBody,
+    Registry: PollableRegistry<Pollable = Body::Pollable>,
+{
+    type Item = Result<Bytes, Error>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.get_mut().poll_next_data(cx)
+    }
+}
+
+/// The data of an [`IncomingHttpBody`] as an async reader. Once it reaches
+/// EOF, the body's trailers are available from
+/// [`IncomingBodyReader::trailers`].
+pub struct IncomingBodyReader<Body, Registry>
+where
+    Body: WasiIncomingBody,
+    Registry: PollableRegistry,
+{
+    stream: IncomingBodyStream<Body, Registry>,
+    // Read from the stream but not yet returned
+    chunk: Bytes,
+}
+
+impl<Body, Registry> IncomingBodyReader<Body, Registry>
+where
+    Body: WasiIncomingBody,
+    Registry: PollableRegistry<Pollable = Body::Pollable>,
+{
+    /// Reads into `buf`, returning 0 at the end of the body.
+    pub fn poll_read(&mut self, cx: &mut Context, buf: &mut [u8]) -> Poll<io::Result<usize>> {
+        if buf.is_empty() {
+            return Poll::Ready(Ok(0));
+        }
+        if self.chunk.is_empty() {
+            match ready!(self.stream.poll_next_data(cx)) {
+                Some(Ok(chunk)) => self.chunk = chunk,
+                Some(Err(err)) => return Poll::Ready(Err(err.into())),
+                None => return Poll::Ready(Ok(0)),
+            }
+        }
+        let len = buf.len().min(self.chunk.len());
+        buf[..len].copy_from_slice(&self.chunk.split_to(len));
+        Poll::Ready(Ok(len))
+    }
+
+    /// Waits for the trailers, discarding any data not yet read.
+    pub async fn trailers(self) -> Result<Option<FieldEntries>, Error> {
+        self.stream.trailers().await
+    }
+}
+
+#[cfg(feature = "futures-io")]
+impl<Body, Registry> futures_io::AsyncRead for IncomingBodyReader<Body, Registry>
+where
+    Body: WasiIncomingBody,
+    Registry: PollableRegistry<Pollable = Body::Pollable>,
+{
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        self.get_mut().poll_read(cx, buf)
+    }
+}
+
+#[cfg(feature = "tokio-io")]
+impl<Body, Registry> tokio::io::AsyncRead for IncomingBodyReader<Body, Registry>
+where
+    Body: WasiIncomingBody,
+    Registry: PollableRegistry<Pollable = Body::Pollable>,
+{
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut tokio::io::ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let len = ready!(self.get_mut().poll_read(cx, buf.initialize_unfilled()))?;
+        buf.advance(len);
+        Poll::Ready(Ok(()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        poll::{PollableRegistry, Poller},
+        testing::{
+            MockFields, MockFutureTrailers, MockIncomingBody, MockInputStream, MockPollable,
+        },
+        IncomingHttpBody,
+    };
+
+    fn body_with_trailers() -> MockIncomingBody {
+        let trailers = MockFields::from(vec![("x-checksum".to_string(), b"abc".to_vec())]);
+        MockIncomingBody::new(
+            MockInputStream::with_data("hello world"),
+            MockFutureTrailers::ready(Some(trailers)),
+        )
+    }
+
+    #[test]
+    fn reader_exposes_trailers_after_eof() {
+        let registry = Poller::<MockPollable>::default();
+        let body = IncomingHttpBody::new(body_with_trailers(), registry.clone()).unwrap();
+        let mut reader = body.into_reader();
+
+        let mut data = vec![];
+        let mut buf = [0; 4];
+        loop {
+            let len = registry
+                .block_on(std::future::poll_fn(|cx| reader.poll_read(cx, &mut buf)))
+                .unwrap()
+                .unwrap();
+            if len == 0 {
+                break;
+            }
+            data.extend_from_slice(&buf[..len]);
+        }
+        assert_eq!(data, b"hello world");
+
+        let trailers = registry.block_on(reader.trailers()).unwrap().unwrap();
+        let trailers = trailers.unwrap().into_iter().collect::<Vec<_>>();
+        assert_eq!(trailers, [("x-checksum".to_string(), b"abc".to_vec())]);
+    }
+
+    #[test]
+    fn trailers_skip_unread_data() {
+        let registry = Poller::<MockPollable>::default();
+        let body = IncomingHttpBody::new(body_with_trailers(), registry.clone()).unwrap();
+        let trailers = registry
+            .block_on(body.into_stream().trailers())
+            .unwrap()
+            .unwrap();
+        assert!(trailers.is_some());
+    }
+
+    #[cfg(feature = "stream")]
+    #[test]
+    fn stream_yields_chunks() {
+        use futures_util::TryStreamExt;
+
+        let registry = Poller::<MockPollable>::default();
+        let body = IncomingHttpBody::new(body_with_trailers(), registry.clone()).unwrap();
+        let mut stream = body.into_stream();
+        let chunks: Vec<_> = registry
+            .block_on((&mut stream).try_collect())
+            .unwrap()
+            .unwrap();
+        assert_eq!(chunks.concat(), b"hello world");
+    }
+}
@@ -1,16 +1,23 @@
-use wasi::traits::{WasiError, WasiErrorCode, WasiStreamError};
+use wasi::traits::{WasiError, WasiErrorCode, WasiHeaderError, WasiStreamError};
 
+pub mod coop;
+pub mod executor;
 pub mod outgoing;
 mod incoming;
 pub mod poll;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
+pub mod time;
 pub mod wasi;
 
-pub use incoming::IncomingHttpBody;
+pub use incoming::{IncomingBodyReader, IncomingBodyStream, IncomingHttpBody};
 
 #[cfg(feature = "hyperium0")]
 pub mod hyperium0;
 #[cfg(feature = "hyperium1")]
 pub mod hyperium1;
+#[cfg(feature = "hyperium1")]
+pub mod proxy;
 
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
@@ -19,19 +26,28 @@ pub enum Error {
 
     #[error("{0}")]
     WasiError(String),
-    #[error("{0}")]
-    WasiErrorCode(String),
-    #[error("{0}")]
-    WasiFieldsError(String),
+    #[error(transparent)]
+    WasiErrorCode(wasi::ErrorCode),
+    #[error(transparent)]
+    WasiFieldsError(wasi::FieldsError),
     #[error("{0}")]
     WasiInvalidState(&'static str),
     #[error("{0}")]
     WasiInvalidValue(&'static str),
-    #[error("stream error: {0}")]
-    WasiStreamOperationFailed(String),
+    #[error("stream error: {message}")]
+    WasiStreamOperationFailed {
+        message: String,
+        /// The HTTP-level cause of the failure, if the host reported one.
+        error_code: Option<wasi::ErrorCode>,
+    },
     #[error("stream closed")]
     WasiStreamClosed,
 
+    #[error(transparent)]
+    Elapsed(#[from] time::Elapsed),
+    #[error(transparent)]
+    BlockOnError(#[from] poll::BlockOnError),
+
     #[cfg(feature = "hyperium0")]
     #[error(transparent)]
     Hyperium0Error(#[from] http0::Error),
@@ -42,15 +58,40 @@ pub enum Error {
 
 impl Error {
     fn wasi_error_code(err: impl WasiErrorCode) -> Self {
-        Self::WasiErrorCode(err.to_string())
+        Self::WasiErrorCode(err.to_error_code())
+    }
+
+    fn wasi_fields_error(name: &str, err: impl WasiHeaderError) -> Self {
+        Self::WasiFieldsError(wasi::FieldsError {
+            name: name.to_string(),
+            kind: err.kind(),
+        })
     }
 
     fn wasi_stream_error(err: impl WasiStreamError) -> Self {
         match err.into_stream_error() {
-            wasi::StreamError::LastOperationFailed(err) => {
-                Self::WasiStreamOperationFailed(err.to_debug_string())
-            }
+            wasi::StreamError::LastOperationFailed(err) => Self::WasiStreamOperationFailed {
+                message: err.to_debug_string(),
+                error_code: err.http_error_code(),
+            },
             wasi::StreamError::Closed => Self::WasiStreamClosed,
         }
     }
 }
+
+impl From<poll::Stalled> for Error {
+    fn from(err: poll::Stalled) -> Self {
+        Self::BlockOnError(err.into())
+    }
+}
+
+impl From<Error> for std::io::Error {
+    fn from(err: Error) -> Self {
+        let kind = match err {
+            Error::WasiStreamClosed => std::io::ErrorKind::BrokenPipe,
+            Error::Elapsed(_) => std::io::ErrorKind::TimedOut,
+            _ => std::io::ErrorKind::Other,
+        };
+        Self::new(kind, err)
+    }
+}
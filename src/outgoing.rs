@@ -1,10 +1,20 @@
 use std::{
     future::Future,
     pin::Pin,
-    task::{Context, Poll},
+    task::{ready, Context, Poll},
 };
 
-use crate::Error;
+use crate::{
+    poll::PollableRegistry,
+    wasi::{
+        traits::{WasiIncomingBody, WasiOutgoingBody, WasiOutputStream},
+        FutureTrailers, IncomingBody, OutgoingBody,
+    },
+    Error,
+};
+
+/// The most each splice asks the host to move.
+const SPLICE_SIZE: u64 = 64 * 1024;
 
 pub enum Copied {
     Body(usize),
@@ -28,6 +38,8 @@ impl<T: OutgoingBodyCopier + Unpin> Future for CopyAllFuture<T> {
     type Output = Result<(), Error>;
 
     fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        // NOTE: the stream operations each copy makes charge the coop budget,
+        // so long copies still yield to other tasks
         loop {
             match self.0.poll_copy(cx) {
                 Poll::Ready(Some(Ok(_))) => (),
@@ -38,3 +50,190 @@ impl<T: OutgoingBodyCopier + Unpin> Future for CopyAllFuture<T> {
         }
     }
 }
+
+/// Copies an [`IncomingBody`] to an [`OutgoingBody`] with `splice`, so the
+/// data moves host-side rather than through guest memory. The incoming
+/// trailers are forwarded when the outgoing body is finished.
+pub struct SpliceCopier<Incoming, Outgoing, Registry>
+where
+    Incoming: WasiIncomingBody,
+    Outgoing: WasiOutgoingBody,
+    Registry: PollableRegistry,
+{
+    src: SpliceSource<Incoming, Registry>,
+    dest: Option<OutgoingBody<Outgoing, Registry>>,
+}
+
+enum SpliceSource<Body: WasiIncomingBody, Registry: PollableRegistry> {
+    Body(IncomingBody<Body, Registry>),
+    Trailers(FutureTrailers<Body::FutureTrailers, Registry>),
+    Empty,
+}
+
+impl<Incoming, Outgoing, Registry> SpliceCopier<Incoming, Outgoing, Registry>
+where
+    Incoming: WasiIncomingBody,
+    Outgoing: WasiOutgoingBody,
+    Registry: PollableRegistry,
+{
+    pub fn new(
+        src: IncomingBody<Incoming, Registry>,
+        dest: OutgoingBody<Outgoing, Registry>,
+    ) -> Self {
+        Self {
+            src: SpliceSource::Body(src),
+            dest: Some(dest),
+        }
+    }
+}
+
+impl<Incoming, Outgoing, Registry> OutgoingBodyCopier for SpliceCopier<Incoming, Outgoing, Registry>
+where
+    Incoming: WasiIncomingBody<Pollable = Registry::Pollable>,
+    Outgoing: WasiOutgoingBody,
+    Outgoing::OutputStream:
+        WasiOutputStream<InputStream = Incoming::InputStream, Pollable = Registry::Pollable>,
+    Registry: PollableRegistry,
+{
+    fn poll_copy(&mut self, cx: &mut Context) -> Poll<Option<Result<Copied, Error>>> {
+        let Some(dest) = &mut self.dest else {
+            return Poll::Ready(None);
+        };
+        match &mut self.src {
+            SpliceSource::Body(src) => {
+                // A splice of 0 bytes only waits on the output stream, so
+                // wait for input first, unless some is already buffered
+                if src.stream().buffered().is_empty() {
+                    ready!(src.stream().poll_ready(cx));
+                }
+                match ready!(dest.stream().poll_splice(cx, src.stream(), SPLICE_SIZE)) {
+                    Ok(len) => Poll::Ready(Some(Ok(Copied::Body(len as usize)))),
+                    Err(Error::WasiStreamClosed) => {
+                        if let SpliceSource::Body(src) =
+                            std::mem::replace(&mut self.src, SpliceSource::Empty)
+                        {
+                            self.src = SpliceSource::Trailers(src.finish());
+                        }
+                        self.poll_copy(cx)
+                    }
+                    Err(err) => Poll::Ready(Some(Err(err))),
+                }
+            }
+            SpliceSource::Trailers(trailers) => {
+                let trailers = ready!(Pin::new(trailers).poll(cx))?;
+                self.src = SpliceSource::Empty;
+                let copied_trailers = trailers.is_some();
+                self.dest.take().unwrap().finish(trailers)?;
+                if copied_trailers {
+                    Poll::Ready(Some(Ok(Copied::Trailers)))
+                } else {
+                    Poll::Ready(None)
+                }
+            }
+            SpliceSource::Empty => Poll::Ready(None),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        future::Future,
+        pin::pin,
+        task::{ready, Context, Poll},
+    };
+
+    use crate::{
+        coop,
+        poll::{noop_waker, PollableRegistry, Poller},
+        testing::{
+            MockFields, MockFutureTrailers, MockIncomingBody, MockInputStream, MockOutgoingBody,
+            MockOutputStream, MockPollable,
+        },
+        wasi::{IncomingBody, OutgoingBody},
+        Error,
+    };
+
+    use super::{Copied, OutgoingBodyCopier, SpliceCopier};
+
+    #[test]
+    fn copy_all_charges_budget_once_per_operation() {
+        // Stands in for a copier whose every stream operation is ready
+        struct Endless<'a>(&'a mut usize);
+
+        impl OutgoingBodyCopier for Endless<'_> {
+            fn poll_copy(&mut self, cx: &mut Context) -> Poll<Option<Result<Copied, Error>>> {
+                ready!(coop::poll_proceed(cx));
+                *self.0 += 1;
+                Poll::Ready(Some(Ok(Copied::Body(1))))
+            }
+        }
+
+        let mut copies = 0;
+        let waker = noop_waker();
+        let mut cx = Context::from_waker(&waker);
+        let mut copy = pin!(Endless(&mut copies).copy_all());
+        let poll = coop::with_budget(|| copy.as_mut().poll(&mut cx));
+        assert!(poll.is_pending());
+        assert_eq!(copies, coop::INITIAL_BUDGET.into());
+    }
+
+    #[test]
+    fn splices_body_and_forwards_trailers() {
+        let registry = Poller::<MockPollable>::default();
+        let input = MockInputStream::new();
+        input.push("hello ");
+        crate::testing::schedule(std::time::Duration::from_secs(1), {
+            let input = input.clone();
+            move || {
+                input.push("world");
+                input.close();
+            }
+        });
+        let trailers = MockFields::from(vec![("x-checksum".to_string(), b"abc".to_vec())]);
+        let incoming = MockIncomingBody::new(input, MockFutureTrailers::ready(Some(trailers)));
+        let src = IncomingBody::new(incoming, registry.clone()).unwrap();
+        let outgoing = MockOutgoingBody::new(MockOutputStream::with_permits([4, 0, 3]));
+        let dest = OutgoingBody::new(outgoing.clone(), registry.clone()).unwrap();
+
+        let copier = SpliceCopier::new(src, dest);
+        registry.block_on(copier.copy_all()).unwrap().unwrap();
+        assert_eq!(outgoing.written(), b"hello world");
+        assert!(outgoing.is_finished());
+        assert_eq!(
+            outgoing.trailers(),
+            Some(vec![("x-checksum".to_string(), b"abc".to_vec())])
+        );
+    }
+
+    #[test]
+    fn splice_copies_data_buffered_before_copying() {
+        let registry = Poller::<MockPollable>::default();
+        let input = MockInputStream::new();
+        input.push("hello ");
+        crate::testing::schedule(std::time::Duration::from_secs(1), {
+            let input = input.clone();
+            move || {
+                input.push("world");
+                input.close();
+            }
+        });
+        let incoming = MockIncomingBody::new(input, MockFutureTrailers::ready(None));
+        let mut src = IncomingBody::new(incoming, registry.clone()).unwrap();
+        let peeked = registry
+            .block_on(std::future::poll_fn(|cx| {
+                src.stream().poll_fill_buf(cx).map_ok(<[u8]>::to_vec)
+            }))
+            .unwrap()
+            .unwrap();
+        assert_eq!(peeked, b"hello ");
+        src.stream().consume(2);
+        let outgoing = MockOutgoingBody::new(MockOutputStream::new());
+        let dest = OutgoingBody::new(outgoing.clone(), registry.clone()).unwrap();
+
+        let copier = SpliceCopier::new(src, dest);
+        registry.block_on(copier.copy_all()).unwrap().unwrap();
+        assert_eq!(outgoing.written(), b"llo world");
+        assert!(outgoing.is_finished());
+    }
+}
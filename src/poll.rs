@@ -1,10 +1,27 @@
 use std::{
     collections::HashMap,
-    sync::{Arc, Mutex, Weak},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex, Weak,
+    },
     task::{Context, Poll, Wake, Waker},
+    time::Instant,
 };
 
-use crate::wasi::traits::{WasiPoll, WasiPollable};
+use crate::{
+    coop,
+    poll::diagnostics::Diagnostics,
+    time::{self, Timeout},
+    wasi::traits::{WasiMonotonicClock, WasiPoll, WasiPollable},
+};
+
+mod diagnostics;
+mod local;
+
+pub use diagnostics::{
+    OutstandingPollable, PollableOrigin, PollerReport, PollerStats, ReportReason,
+};
+pub use local::{LocalPoller, LocalRegisteredPollable};
 
 /// A PollableRegistry manages the polling of Pollables in relation to some
 /// Rust async executor. This must be a cheaply-`clone`able handle to its
@@ -22,34 +39,160 @@ pub trait PollableRegistry: Clone + Unpin {
         pollable: Self::Pollable,
     ) -> Self::RegisteredPollable;
 
+    /// Like [`PollableRegistry::register_pollable`], labelling the pollable
+    /// with where it was subscribed from for diagnostics.
+    fn register_pollable_with_origin(
+        &self,
+        cx: &mut Context,
+        pollable: Self::Pollable,
+        origin: PollableOrigin,
+    ) -> Self::RegisteredPollable {
+        let _ = origin;
+        self.register_pollable(cx, pollable)
+    }
+
+    /// Releases a registered pollable because the resource it was subscribed
+    /// from is about to be dropped.
+    fn release(&self, registered: Self::RegisteredPollable) {
+        drop(registered)
+    }
+
+    /// Adds the given context's waker to a pollable that is already
+    /// registered, so that every waiter is woken when it becomes ready.
+    /// Returns false if the pollable is no longer registered (e.g. because it
+    /// was already ready), in which case the caller should register a new one.
+    /// The default always returns false.
+    fn register_waker(&self, cx: &mut Context, registered: &Self::RegisteredPollable) -> bool {
+        let _ = (cx, registered);
+        false
+    }
+
     /// Poll all pollables. Returns false if there are no active pollables.
     fn poll(&self) -> bool;
 
+    /// Wake any pollables which are already ready, without blocking. The
+    /// default does nothing, leaving them to the next [`PollableRegistry::poll`].
+    fn poll_nonblocking(&self) {}
+
+    /// Poll all pollables along with the given unregistered pollable, e.g. a
+    /// deadline. Returns None if there are no active pollables, otherwise
+    /// whether the given pollable was ready.
+    ///
+    /// The default only checks the given pollable before and after a
+    /// [`PollableRegistry::poll`], so it isn't noticed until a registered
+    /// pollable is also ready; registries should poll them together.
+    fn poll_with(&self, pollable: &Self::Pollable) -> Option<bool> {
+        if pollable.ready() {
+            return Some(true);
+        }
+        if !self.poll() {
+            return None;
+        }
+        Some(pollable.ready())
+    }
+
+    /// Called when a future is left pending with no active pollables, just
+    /// before Err(Stalled) is returned. The default does nothing.
+    fn report_stall(&self) {}
+
     /// Runs the given future to completion, polling any WASI pollables that
     /// are registered with this registry. Returns Err(Stalled) if there are no
     /// active pollables while the future is pending.
+    ///
+    /// The future is only polled again after its waker has been called.
     fn block_on<T>(&self, fut: impl std::future::Future<Output = T>) -> Result<T, Stalled> {
         let mut fut = std::pin::pin!(fut);
-        let waker = noop_waker();
+        let woken = Arc::new(WakeFlag::new(true));
+        let waker = woken.clone().into();
         let mut cx = Context::from_waker(&waker);
         loop {
-            if let Poll::Ready(val) = fut.as_mut().poll(&mut cx) {
-                return Ok(val);
+            if woken.take() {
+                if let Poll::Ready(val) = coop::with_budget(|| fut.as_mut().poll(&mut cx)) {
+                    return Ok(val);
+                }
+                if woken.is_set() {
+                    // The future woke itself; poll it again without blocking
+                    self.poll_nonblocking();
+                    continue;
+                }
             }
             if !self.poll() {
+                self.report_stall();
                 return Err(Stalled);
             }
         }
     }
+
+    /// Like [`PollableRegistry::block_on`], but returns
+    /// Err(BlockOnError::TimedOut) if the future is still pending once the
+    /// monotonic clock reaches `deadline`.
+    fn block_on_with_deadline<T>(
+        &self,
+        deadline: time::Instant,
+        fut: impl std::future::Future<Output = T>,
+    ) -> Result<T, BlockOnError>
+    where
+        Self::Pollable: WasiMonotonicClock,
+    {
+        let mut fut = std::pin::pin!(fut);
+        let woken = Arc::new(WakeFlag::new(true));
+        let waker = woken.clone().into();
+        let mut cx = Context::from_waker(&waker);
+        let deadline = Self::Pollable::subscribe_instant(deadline);
+        loop {
+            if woken.take() {
+                if let Poll::Ready(val) = coop::with_budget(|| fut.as_mut().poll(&mut cx)) {
+                    return Ok(val);
+                }
+                if woken.is_set() {
+                    if deadline.ready() {
+                        return Err(BlockOnError::TimedOut);
+                    }
+                    self.poll_nonblocking();
+                    continue;
+                }
+            }
+            match self.poll_with(&deadline) {
+                None => {
+                    self.report_stall();
+                    return Err(BlockOnError::Stalled);
+                }
+                Some(true) => return Err(BlockOnError::TimedOut),
+                Some(false) => (),
+            }
+        }
+    }
+
+    /// Requires the given future to complete within `duration`. See
+    /// [`time::timeout`].
+    fn timeout<F>(&self, duration: std::time::Duration, fut: F) -> Timeout<Self, F>
+    where
+        Self::Pollable: WasiMonotonicClock,
+        F: std::future::Future,
+    {
+        time::timeout(self.clone(), duration, fut)
+    }
 }
 
 pub struct Poller<Pollable: WasiPoll> {
     entries: Arc<Mutex<HashMap<u32, Entry<Pollable>>>>,
+    diagnostics: Option<Arc<Diagnostics>>,
 }
 
 struct Entry<Pollable: WasiPollable> {
     pollable: Weak<Pollable>,
-    waker: Waker,
+    wakers: Vec<Waker>,
+    origin: PollableOrigin,
+    // Only recorded with diagnostics enabled
+    registered_at: Option<Instant>,
+}
+
+impl<Pollable: WasiPollable> Entry<Pollable> {
+    fn add_waker(&mut self, waker: &Waker) {
+        if !self.wakers.iter().any(|w| w.will_wake(waker)) {
+            self.wakers.push(waker.clone());
+        }
+    }
 }
 
 impl<Pollable: WasiPoll> PollableRegistry for Poller<Pollable> {
@@ -60,25 +203,160 @@ impl<Pollable: WasiPoll> PollableRegistry for Poller<Pollable> {
         &self,
         cx: &mut Context,
         pollable: Self::Pollable,
+    ) -> Self::RegisteredPollable {
+        self.register_pollable_with_origin(cx, pollable, PollableOrigin::Unknown)
+    }
+
+    fn register_pollable_with_origin(
+        &self,
+        cx: &mut Context,
+        pollable: Self::Pollable,
+        origin: PollableOrigin,
     ) -> Self::RegisteredPollable {
         let handle = pollable.handle();
         let pollable = Arc::new(pollable);
-        let entry = Entry {
-            pollable: Arc::downgrade(&pollable),
-            waker: cx.waker().clone(),
-        };
-        self.entries.lock().unwrap().insert(handle, entry);
+        let mut entries = self.entries.lock().unwrap();
+        let entry = entries.entry(handle).or_insert_with(|| Entry {
+            pollable: Weak::new(),
+            wakers: vec![],
+            origin,
+            registered_at: None,
+        });
+        if entry.pollable.strong_count() == 0 {
+            // Handles may be reused once a pollable is dropped
+            entry.wakers.clear();
+            entry.origin = origin;
+            entry.registered_at = self.diagnostics.as_ref().map(|_| Instant::now());
+        }
+        entry.pollable = Arc::downgrade(&pollable);
+        entry.add_waker(cx.waker());
         pollable
     }
 
+    fn release(&self, registered: Self::RegisteredPollable) {
+        let Some(diagnostics) = &self.diagnostics else {
+            return;
+        };
+        let handle = registered.handle();
+        let pollable = Arc::downgrade(&registered);
+        drop(registered);
+        let mut entries = self.entries.lock().unwrap();
+        // Ready pollables are removed when polled, so this one is still
+        // awaited by whoever registered it
+        let pending = entries
+            .get(&handle)
+            .filter(|entry| entry.pollable.ptr_eq(&pollable))
+            .map(|entry| entry.origin);
+        if pending.is_some() {
+            entries.remove(&handle);
+        }
+        drop(entries);
+        // The parent is dropped next, so nothing may still hold or await the
+        // pollable
+        if pending.is_some() || pollable.strong_count() > 0 {
+            let origin = pending.unwrap_or(PollableOrigin::Unknown);
+            let report =
+                diagnostics.report(ReportReason::Leaked { handle, origin }, self.outstanding());
+            diagnostics.emit(&report);
+        }
+    }
+
+    fn register_waker(&self, cx: &mut Context, registered: &Self::RegisteredPollable) -> bool {
+        let mut entries = self.entries.lock().unwrap();
+        match entries.get_mut(&registered.handle()) {
+            Some(entry) if entry.pollable.ptr_eq(&Arc::downgrade(registered)) => {
+                entry.add_waker(cx.waker());
+                true
+            }
+            _ => false,
+        }
+    }
+
     fn poll(&self) -> bool {
+        self.poll_inner(None).is_some()
+    }
+
+    fn poll_nonblocking(&self) {
+        let mut entries = self.entries.lock().unwrap();
+        let mut wakers = vec![];
+        entries.retain(|_, entry| match entry.pollable.upgrade() {
+            Some(pollable) if pollable.ready() => {
+                wakers.append(&mut entry.wakers);
+                false
+            }
+            Some(_) => true,
+            None => false,
+        });
+        drop(entries);
+        for waker in wakers {
+            waker.wake();
+        }
+    }
+
+    fn poll_with(&self, pollable: &Self::Pollable) -> Option<bool> {
+        self.poll_inner(Some(pollable))
+    }
+
+    fn report_stall(&self) {
+        if let Some(diagnostics) = &self.diagnostics {
+            diagnostics.emit(&diagnostics.report(ReportReason::Stalled, self.outstanding()));
+        }
+    }
+}
+
+impl<Pollable: WasiPoll> Poller<Pollable> {
+    /// Returns a Poller which records diagnostics, writing a report to stderr
+    /// if it stalls or a registered pollable is still pending when its parent
+    /// resource is dropped.
+    pub fn with_diagnostics() -> Self {
+        Self::with_diagnostics_handler(|report| eprint!("{report}"))
+    }
+
+    /// Returns a Poller which records diagnostics, calling the given handler
+    /// with a report if it stalls or a registered pollable is still pending
+    /// when its parent resource is dropped.
+    pub fn with_diagnostics_handler(
+        handler: impl Fn(&PollerReport) + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            entries: Default::default(),
+            diagnostics: Some(Arc::new(Diagnostics::new(handler))),
+        }
+    }
+
+    /// Returns a report of the current diagnostics, or None if diagnostics
+    /// are not enabled.
+    pub fn report(&self) -> Option<PollerReport> {
+        let diagnostics = self.diagnostics.as_ref()?;
+        Some(diagnostics.report(ReportReason::Requested, self.outstanding()))
+    }
+
+    fn outstanding(&self) -> Vec<OutstandingPollable> {
+        self.entries
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(_, entry)| entry.pollable.strong_count() > 0)
+            .map(|(&handle, entry)| OutstandingPollable {
+                handle,
+                origin: entry.origin,
+                waiters: entry.wakers.len(),
+                age: entry
+                    .registered_at
+                    .map(|at| at.elapsed())
+                    .unwrap_or_default(),
+            })
+            .collect()
+    }
+
+    fn poll_inner(&self, extra: Option<&Pollable>) -> Option<bool> {
         let mut entries = self.entries.lock().unwrap();
 
         // Remove any dropped pollables
         entries.retain(|_, entry| entry.pollable.strong_count() > 0);
 
         if entries.is_empty() {
-            return false;
+            return None;
         }
 
         // Poll pollables
@@ -86,17 +364,35 @@ impl<Pollable: WasiPoll> PollableRegistry for Poller<Pollable> {
             .values()
             .filter_map(|entry| entry.pollable.upgrade())
             .collect::<Vec<_>>();
-        let pollable_refs = pollables.iter().map(|p| p.as_ref()).collect::<Vec<_>>();
+        let mut pollable_refs = pollables.iter().map(|p| p.as_ref()).collect::<Vec<_>>();
+        pollable_refs.extend(extra);
+        let started = self.diagnostics.as_ref().map(|_| Instant::now());
         let ready_idxs = Pollable::poll(&pollable_refs);
+        let blocked = started.map(|started| started.elapsed());
 
-        // Remove and wake any ready pollables
+        // Remove any ready pollables, waking all of their waiters
+        let mut wakers = vec![];
+        let mut ready_origins = vec![];
+        let mut extra_ready = false;
         for idx in ready_idxs {
             let idx: usize = idx.try_into().unwrap();
+            if idx == pollables.len() {
+                extra_ready = true;
+                continue;
+            }
             let handle = pollables[idx].handle();
             let entry = entries.remove(&handle).unwrap();
-            entry.waker.wake();
+            wakers.extend(entry.wakers);
+            ready_origins.push(entry.origin);
+        }
+        drop(entries);
+        if let (Some(diagnostics), Some(blocked)) = (&self.diagnostics, blocked) {
+            diagnostics.record_poll(blocked, &ready_origins);
         }
-        true
+        for waker in wakers {
+            waker.wake();
+        }
+        Some(extra_ready)
     }
 }
 
@@ -104,6 +400,7 @@ impl<Pollable: WasiPoll> Clone for Poller<Pollable> {
     fn clone(&self) -> Self {
         Self {
             entries: self.entries.clone(),
+            diagnostics: self.diagnostics.clone(),
         }
     }
 }
@@ -112,10 +409,39 @@ impl<Pollable: WasiPoll> Default for Poller<Pollable> {
     fn default() -> Self {
         Self {
             entries: Default::default(),
+            diagnostics: None,
         }
     }
 }
 
+/// A waker which records that it was called.
+pub(crate) struct WakeFlag(AtomicBool);
+
+impl WakeFlag {
+    pub(crate) fn new(woken: bool) -> Self {
+        Self(AtomicBool::new(woken))
+    }
+
+    /// Clears the flag, returning whether it was set.
+    pub(crate) fn take(&self) -> bool {
+        self.0.swap(false, Ordering::SeqCst)
+    }
+
+    pub(crate) fn is_set(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+}
+
+impl Wake for WakeFlag {
+    fn wake(self: Arc<Self>) {
+        self.wake_by_ref()
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+}
+
 pub fn noop_waker() -> Waker {
     struct NoopWaker;
     impl Wake for NoopWaker {
@@ -134,3 +460,234 @@ impl std::fmt::Display for Stalled {
 }
 
 impl std::error::Error for Stalled {}
+
+#[derive(Debug)]
+pub enum BlockOnError {
+    Stalled,
+    TimedOut,
+}
+
+impl From<Stalled> for BlockOnError {
+    fn from(_: Stalled) -> Self {
+        Self::Stalled
+    }
+}
+
+impl std::fmt::Display for BlockOnError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Stalled => Stalled.fmt(f),
+            Self::TimedOut => write!(f, "future did not complete before its deadline"),
+        }
+    }
+}
+
+impl std::error::Error for BlockOnError {}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        cell::Cell,
+        future::{pending, poll_fn},
+        rc::Rc,
+        sync::{Arc, Mutex},
+        task::{Context, Poll, Waker},
+        time::Duration,
+    };
+
+    use crate::{
+        testing::{self, MockInputStream, MockPollable},
+        time,
+        wasi::{traits::WasiPollable, InputStream},
+    };
+
+    use super::{
+        noop_waker, BlockOnError, PollableOrigin, PollableRegistry, Poller, PollerReport,
+        ReportReason, Stalled, WakeFlag,
+    };
+
+    fn recording_poller() -> (Poller<MockPollable>, Arc<Mutex<Vec<PollerReport>>>) {
+        let reports = Arc::new(Mutex::new(vec![]));
+        let poller = Poller::with_diagnostics_handler({
+            let reports = reports.clone();
+            move |report| reports.lock().unwrap().push(report.clone())
+        });
+        (poller, reports)
+    }
+
+    #[test]
+    fn wakes_every_waiter_on_one_handle() {
+        let poller = Poller::<MockPollable>::default();
+        let ready = Rc::new(Cell::new(false));
+        testing::schedule(Duration::from_millis(1), {
+            let ready = ready.clone();
+            move || ready.set(true)
+        });
+        let pollable = MockPollable::new(move || ready.get());
+
+        let first = Arc::new(WakeFlag::new(false));
+        let second = Arc::new(WakeFlag::new(false));
+        let first_waker = Waker::from(first.clone());
+        let second_waker = Waker::from(second.clone());
+        let registered = poller.register_pollable(&mut Context::from_waker(&first_waker), pollable);
+        assert!(poller.register_waker(&mut Context::from_waker(&second_waker), &registered));
+
+        assert!(poller.poll());
+        assert!(first.is_set());
+        assert!(second.is_set());
+        // Once ready the pollable is unregistered, so waiters must subscribe again
+        assert!(!poller.register_waker(&mut Context::from_waker(&first_waker), &registered));
+    }
+
+    #[test]
+    fn registry_with_only_required_methods() {
+        #[derive(Clone, Default)]
+        struct Minimal(Poller<MockPollable>);
+
+        impl PollableRegistry for Minimal {
+            type Pollable = MockPollable;
+            type RegisteredPollable = Arc<MockPollable>;
+
+            fn register_pollable(
+                &self,
+                cx: &mut Context,
+                pollable: Self::Pollable,
+            ) -> Self::RegisteredPollable {
+                self.0.register_pollable(cx, pollable)
+            }
+
+            fn poll(&self) -> bool {
+                self.0.poll()
+            }
+        }
+
+        let registry = Minimal::default();
+        let start = testing::now();
+        registry
+            .block_on(time::sleep(registry.clone(), Duration::from_millis(5)))
+            .unwrap();
+        assert_eq!(testing::now() - start, 5_000_000);
+        let res = registry.block_on_with_deadline(
+            testing::now() + 1_000,
+            time::sleep(registry.clone(), Duration::from_secs(1)),
+        );
+        assert!(matches!(res, Err(BlockOnError::TimedOut)));
+    }
+
+    #[test]
+    fn block_on_only_repolls_when_woken() {
+        let poller = Poller::<MockPollable>::default();
+        let ready = Rc::new(Cell::new(false));
+        testing::schedule(Duration::from_millis(1), {
+            let ready = ready.clone();
+            move || ready.set(true)
+        });
+        // Registered with a different waker, so its event doesn't wake the future
+        let waker = noop_waker();
+        let _registered = poller.register_pollable(
+            &mut Context::from_waker(&waker),
+            MockPollable::new(move || ready.get()),
+        );
+
+        let start = testing::now();
+        let polls = Cell::new(0);
+        let res = poller.block_on(poll_fn(|_| {
+            polls.set(polls.get() + 1);
+            Poll::<()>::Pending
+        }));
+        assert!(matches!(res, Err(Stalled)));
+        assert_eq!(testing::now() - start, 1_000_000);
+        assert_eq!(polls.get(), 1);
+    }
+
+    #[test]
+    fn deadline_times_out_pending_pollable() {
+        let registry = Poller::<MockPollable>::default();
+        let never = time::sleep(registry.clone(), Duration::from_secs(60));
+        let deadline = testing::now() + 1_000;
+        let res = registry.block_on_with_deadline(deadline, never);
+        assert!(matches!(res, Err(BlockOnError::TimedOut)));
+    }
+
+    #[test]
+    fn reports_stall() {
+        let (poller, reports) = recording_poller();
+        assert!(matches!(poller.block_on(pending::<()>()), Err(Stalled)));
+        let reports = reports.lock().unwrap();
+        assert_eq!(reports.len(), 1);
+        assert!(matches!(reports[0].reason, ReportReason::Stalled));
+    }
+
+    #[test]
+    fn reports_pollable_outliving_its_parent() {
+        let (poller, reports) = recording_poller();
+        let mut pollable = Some(MockPollable::new(|| false));
+        let handle = pollable.as_ref().unwrap().handle();
+        let registered = poller
+            .block_on(poll_fn(|cx| {
+                Poll::Ready(poller.register_pollable(cx, pollable.take().unwrap()))
+            }))
+            .unwrap();
+        let leaked = registered.clone();
+        poller.release(registered);
+
+        let reports = reports.lock().unwrap();
+        assert_eq!(reports.len(), 1);
+        assert!(matches!(
+            reports[0].reason,
+            ReportReason::Leaked { handle: h, .. } if h == handle
+        ));
+        assert_eq!(reports[0].stats.leaked, 1);
+        drop(leaked);
+    }
+
+    #[test]
+    fn reports_stream_dropped_while_pending() {
+        let (poller, reports) = recording_poller();
+        let mut stream = InputStream::new(MockInputStream::new(), poller.clone());
+        let pending = poller
+            .block_on(poll_fn(|cx| {
+                Poll::Ready(stream.poll_read(cx, 1).is_pending())
+            }))
+            .unwrap();
+        assert!(pending);
+        assert_eq!(poller.report().unwrap().outstanding.len(), 1);
+        drop(stream);
+
+        let reports = reports.lock().unwrap();
+        assert_eq!(reports.len(), 1);
+        assert!(matches!(
+            reports[0].reason,
+            ReportReason::Leaked {
+                origin: PollableOrigin::InputStream,
+                ..
+            }
+        ));
+        assert!(reports[0].outstanding.is_empty());
+    }
+
+    #[test]
+    fn dropping_a_stream_once_read_is_not_a_leak() {
+        let (poller, reports) = recording_poller();
+        let source = MockInputStream::new();
+        let mut stream = InputStream::new(source.clone(), poller.clone());
+        testing::schedule(Duration::from_millis(1), move || source.push(b"a"));
+        let read = poller
+            .block_on(poll_fn(|cx| stream.poll_read(cx, 1)))
+            .unwrap()
+            .unwrap();
+        assert_eq!(read, b"a");
+        drop(stream);
+
+        assert!(reports.lock().unwrap().is_empty());
+        assert!(poller.report().unwrap().outstanding.is_empty());
+    }
+
+    #[test]
+    fn poll_without_registrations_reports_nothing() {
+        let (poller, reports) = recording_poller();
+        assert!(!poller.poll());
+        assert_eq!(poller.poll_with(&MockPollable::new(|| true)), None);
+        assert!(reports.lock().unwrap().is_empty());
+    }
+}
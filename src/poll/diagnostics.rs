@@ -0,0 +1,158 @@
+use std::{fmt, sync::Mutex, time::Duration};
+
+/// Where a registered pollable was subscribed from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum PollableOrigin {
+    InputStream,
+    OutputStream,
+    FutureTrailers,
+    FutureIncomingResponse,
+    Timer,
+    Unknown,
+}
+
+impl fmt::Display for PollableOrigin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::InputStream => "input-stream",
+            Self::OutputStream => "output-stream",
+            Self::FutureTrailers => "future-trailers",
+            Self::FutureIncomingResponse => "future-incoming-response",
+            Self::Timer => "timer",
+            Self::Unknown => "unknown",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Counters recorded by a [`Poller`](super::Poller) with diagnostics enabled.
+#[derive(Clone, Debug, Default)]
+pub struct PollerStats {
+    /// Number of calls to `WasiPoll::poll`.
+    pub iterations: u64,
+    /// Number of pollables reported ready.
+    pub ready: u64,
+    /// Total time spent blocked in `WasiPoll::poll`.
+    pub blocked: Duration,
+    /// Number of registered pollables which were still pending when their
+    /// parent resource was dropped.
+    pub leaked: u64,
+}
+
+#[derive(Clone, Debug)]
+pub enum ReportReason {
+    /// Requested with [`Poller::report`](super::Poller::report).
+    Requested,
+    /// A future was still pending with no active pollables left to poll.
+    Stalled,
+    /// A registered pollable was still pending, or still held elsewhere, when
+    /// the resource it was subscribed from was dropped.
+    Leaked { handle: u32, origin: PollableOrigin },
+}
+
+#[derive(Clone, Debug)]
+pub struct OutstandingPollable {
+    pub handle: u32,
+    pub origin: PollableOrigin,
+    pub waiters: usize,
+    /// Time since the pollable was registered.
+    pub age: Duration,
+}
+
+#[derive(Clone, Debug)]
+pub struct PollerReport {
+    pub reason: ReportReason,
+    pub stats: PollerStats,
+    pub outstanding: Vec<OutstandingPollable>,
+    /// Origins of the pollables reported ready by the last poll.
+    pub last_ready: Vec<PollableOrigin>,
+}
+
+impl fmt::Display for PollerReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.reason {
+            ReportReason::Requested => writeln!(f, "poller report:")?,
+            ReportReason::Stalled => writeln!(f, "poller stalled with no remaining pollables:")?,
+            ReportReason::Leaked { handle, origin } => writeln!(
+                f,
+                "pollable {handle} ({origin}) was still pending when its parent resource was dropped:"
+            )?,
+        }
+        let PollerStats {
+            iterations,
+            ready,
+            blocked,
+            leaked,
+        } = &self.stats;
+        writeln!(
+            f,
+            "  iterations={iterations} ready={ready} blocked={blocked:?} leaked={leaked}"
+        )?;
+        if !self.last_ready.is_empty() {
+            let origins = self
+                .last_ready
+                .iter()
+                .map(|origin| origin.to_string())
+                .collect::<Vec<_>>();
+            writeln!(f, "  last ready: {}", origins.join(", "))?;
+        }
+        for pollable in &self.outstanding {
+            writeln!(
+                f,
+                "  outstanding: handle={} origin={} waiters={} age={:?}",
+                pollable.handle, pollable.origin, pollable.waiters, pollable.age
+            )?;
+        }
+        Ok(())
+    }
+}
+
+pub(super) struct Diagnostics {
+    state: Mutex<DiagnosticsState>,
+    handler: Box<dyn Fn(&PollerReport) + Send + Sync>,
+}
+
+#[derive(Default)]
+struct DiagnosticsState {
+    stats: PollerStats,
+    last_ready: Vec<PollableOrigin>,
+}
+
+impl Diagnostics {
+    pub(super) fn new(handler: impl Fn(&PollerReport) + Send + Sync + 'static) -> Self {
+        Self {
+            state: Default::default(),
+            handler: Box::new(handler),
+        }
+    }
+
+    pub(super) fn record_poll(&self, blocked: Duration, ready: &[PollableOrigin]) {
+        let mut state = self.state.lock().unwrap();
+        state.stats.iterations += 1;
+        state.stats.ready += ready.len() as u64;
+        state.stats.blocked += blocked;
+        state.last_ready.clear();
+        state.last_ready.extend_from_slice(ready);
+    }
+
+    pub(super) fn report(
+        &self,
+        reason: ReportReason,
+        outstanding: Vec<OutstandingPollable>,
+    ) -> PollerReport {
+        let mut state = self.state.lock().unwrap();
+        if let ReportReason::Leaked { .. } = reason {
+            state.stats.leaked += 1;
+        }
+        PollerReport {
+            reason,
+            stats: state.stats.clone(),
+            outstanding,
+            last_ready: state.last_ready.clone(),
+        }
+    }
+
+    pub(super) fn emit(&self, report: &PollerReport) {
+        (self.handler)(report)
+    }
+}
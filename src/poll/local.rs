@@ -0,0 +1,276 @@
+use std::{
+    cell::RefCell,
+    rc::{Rc, Weak},
+    task::{Context, Waker},
+};
+
+use crate::wasi::traits::WasiPoll;
+
+use super::PollableRegistry;
+
+/// A single-threaded [`PollableRegistry`]. Unlike [`Poller`](super::Poller),
+/// this uses no locking, keeps its entries in a slab that registrations index
+/// directly, and reuses its key and waker buffers between calls to `poll`.
+/// The list of pollables passed to the host borrows from the slab, so it is
+/// still built on each call.
+pub struct LocalPoller<Pollable: WasiPoll> {
+    state: Rc<RefCell<State<Pollable>>>,
+}
+
+struct State<Pollable> {
+    entries: Vec<Option<Entry<Pollable>>>,
+    free: Vec<usize>,
+    // Scratch buffers reused by each poll
+    keys: Vec<usize>,
+    wakers: Vec<Waker>,
+}
+
+struct Entry<Pollable> {
+    pollable: Pollable,
+    wakers: Vec<Waker>,
+    // false once the pollable has been ready
+    active: bool,
+}
+
+/// A pollable registered with a [`LocalPoller`]. The pollable is dropped
+/// along with this.
+pub struct LocalRegisteredPollable<Pollable> {
+    state: Weak<RefCell<State<Pollable>>>,
+    key: usize,
+}
+
+impl<Pollable: WasiPoll> PollableRegistry for LocalPoller<Pollable> {
+    type Pollable = Pollable;
+    type RegisteredPollable = LocalRegisteredPollable<Pollable>;
+
+    fn register_pollable(
+        &self,
+        cx: &mut Context,
+        pollable: Self::Pollable,
+    ) -> Self::RegisteredPollable {
+        let mut state = self.state.borrow_mut();
+        let entry = Entry {
+            pollable,
+            wakers: vec![cx.waker().clone()],
+            active: true,
+        };
+        let key = match state.free.pop() {
+            Some(key) => {
+                state.entries[key] = Some(entry);
+                key
+            }
+            None => {
+                state.entries.push(Some(entry));
+                state.entries.len() - 1
+            }
+        };
+        LocalRegisteredPollable {
+            state: Rc::downgrade(&self.state),
+            key,
+        }
+    }
+
+    fn register_waker(&self, cx: &mut Context, registered: &Self::RegisteredPollable) -> bool {
+        if !registered.state.ptr_eq(&Rc::downgrade(&self.state)) {
+            return false;
+        }
+        let mut state = self.state.borrow_mut();
+        match &mut state.entries[registered.key] {
+            Some(entry) if entry.active => {
+                if !entry.wakers.iter().any(|w| w.will_wake(cx.waker())) {
+                    entry.wakers.push(cx.waker().clone());
+                }
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn poll(&self) -> bool {
+        self.poll_inner(None).is_some()
+    }
+
+    fn poll_nonblocking(&self) {
+        let mut state = self.state.borrow_mut();
+        let State {
+            entries, wakers, ..
+        } = &mut *state;
+        for entry in entries.iter_mut().flatten() {
+            if entry.active && entry.pollable.ready() {
+                entry.active = false;
+                wakers.append(&mut entry.wakers);
+            }
+        }
+        let mut ready_wakers = std::mem::take(wakers);
+        drop(state);
+        for waker in ready_wakers.drain(..) {
+            waker.wake();
+        }
+        self.state.borrow_mut().wakers = ready_wakers;
+    }
+
+    fn poll_with(&self, pollable: &Self::Pollable) -> Option<bool> {
+        self.poll_inner(Some(pollable))
+    }
+}
+
+impl<Pollable: WasiPoll> LocalPoller<Pollable> {
+    fn poll_inner(&self, extra: Option<&Pollable>) -> Option<bool> {
+        let mut state = self.state.borrow_mut();
+        let State {
+            entries,
+            keys,
+            wakers,
+            ..
+        } = &mut *state;
+
+        keys.clear();
+        keys.extend(
+            entries.iter().enumerate().filter_map(|(key, entry)| {
+                entry.as_ref().filter(|entry| entry.active).map(|_| key)
+            }),
+        );
+        if keys.is_empty() {
+            return None;
+        }
+
+        // Poll pollables
+        let pollable_refs: Vec<&Pollable> = keys
+            .iter()
+            .map(|&key| &entries[key].as_ref().unwrap().pollable)
+            .chain(extra)
+            .collect();
+        let ready_idxs = Pollable::poll(&pollable_refs);
+
+        // Deactivate any ready pollables, waking all of their waiters
+        let mut extra_ready = false;
+        for idx in ready_idxs {
+            let idx: usize = idx.try_into().unwrap();
+            if idx == keys.len() {
+                extra_ready = true;
+                continue;
+            }
+            let entry = entries[keys[idx]].as_mut().unwrap();
+            entry.active = false;
+            wakers.append(&mut entry.wakers);
+        }
+        let mut ready_wakers = std::mem::take(wakers);
+        drop(state);
+        for waker in ready_wakers.drain(..) {
+            waker.wake();
+        }
+        self.state.borrow_mut().wakers = ready_wakers;
+        Some(extra_ready)
+    }
+}
+
+impl<Pollable> Drop for LocalRegisteredPollable<Pollable> {
+    fn drop(&mut self) {
+        if let Some(state) = self.state.upgrade() {
+            let mut state = state.borrow_mut();
+            let entry = state.entries[self.key].take();
+            state.free.push(self.key);
+            drop(state);
+            drop(entry);
+        }
+    }
+}
+
+impl<Pollable: WasiPoll> Clone for LocalPoller<Pollable> {
+    fn clone(&self) -> Self {
+        Self {
+            state: self.state.clone(),
+        }
+    }
+}
+
+impl<Pollable: WasiPoll> Default for LocalPoller<Pollable> {
+    fn default() -> Self {
+        Self {
+            state: Rc::new(RefCell::new(State {
+                entries: vec![],
+                free: vec![],
+                keys: vec![],
+                wakers: vec![],
+            })),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        cell::Cell,
+        future::pending,
+        rc::Rc,
+        sync::Arc,
+        task::{Context, Waker},
+        time::Duration,
+    };
+
+    use crate::{
+        poll::{PollableRegistry, Stalled, WakeFlag},
+        testing::{self, MockPollable},
+        time,
+    };
+
+    use super::LocalPoller;
+
+    fn ready_after(delay: Duration) -> MockPollable {
+        let ready = Rc::new(Cell::new(false));
+        testing::schedule(delay, {
+            let ready = ready.clone();
+            move || ready.set(true)
+        });
+        MockPollable::new(move || ready.get())
+    }
+
+    #[test]
+    fn block_on_drives_timers() {
+        let registry = LocalPoller::<MockPollable>::default();
+        let start = testing::now();
+        registry
+            .block_on(time::sleep(registry.clone(), Duration::from_secs(1)))
+            .unwrap();
+        assert_eq!(testing::now() - start, 1_000_000_000);
+        assert!(matches!(registry.block_on(pending::<()>()), Err(Stalled)));
+    }
+
+    #[test]
+    fn wakes_every_waiter_once_ready() {
+        let registry = LocalPoller::<MockPollable>::default();
+        let first = Arc::new(WakeFlag::new(false));
+        let second = Arc::new(WakeFlag::new(false));
+        let first_waker = Waker::from(first.clone());
+        let second_waker = Waker::from(second.clone());
+        let registered = registry.register_pollable(
+            &mut Context::from_waker(&first_waker),
+            ready_after(Duration::from_millis(1)),
+        );
+        assert!(registry.register_waker(&mut Context::from_waker(&second_waker), &registered));
+
+        assert!(registry.poll());
+        assert!(first.is_set());
+        assert!(second.is_set());
+        assert!(!registry.register_waker(&mut Context::from_waker(&first_waker), &registered));
+        // Ready pollables are no longer polled
+        assert!(!registry.poll());
+    }
+
+    #[test]
+    fn dropping_registration_frees_its_slot() {
+        let registry = LocalPoller::<MockPollable>::default();
+        let waker = Waker::from(Arc::new(WakeFlag::new(false)));
+        let mut cx = Context::from_waker(&waker);
+        let first = registry.register_pollable(&mut cx, MockPollable::new(|| false));
+        let key = first.key;
+        drop(first);
+        assert!(!registry.poll());
+
+        let second = registry.register_pollable(&mut cx, MockPollable::new(|| false));
+        assert_eq!(second.key, key);
+        registry.release(second);
+        assert!(registry.state.borrow().entries[key].is_none());
+        assert!(!registry.poll());
+    }
+}
@@ -0,0 +1,552 @@
+//! A reverse proxy which forwards an incoming request to an upstream server
+//! and streams the response back, splicing both bodies host-side.
+
+use std::{
+    future::{poll_fn, Future},
+    pin::pin,
+    task::Poll,
+};
+
+use crate::{
+    hyperium1::{start, IncomingResponseBody, RequestFuture, WasiRequest},
+    outgoing::{OutgoingBodyCopier, SpliceCopier},
+    poll::PollableRegistry,
+    wasi::{
+        traits::{
+            WasiFutureIncomingResponse, WasiHttpBindings, WasiIncomingBody, WasiIncomingRequest,
+            WasiIncomingResponse, WasiOutgoingBody, WasiOutgoingHandler, WasiOutgoingRequest,
+            WasiOutgoingResponse, WasiOutputStream, WasiResponseOutparam,
+        },
+        ErrorCode, FieldEntries, ForbiddenHeaderPolicy, IncomingRequest, OutgoingRequest,
+        OutgoingResponse, RequestOptions, ResponseOutparam, Scheme,
+    },
+    Error, IncomingHttpBody,
+};
+
+/// Headers which only apply to a single hop: the connection-specific fields of
+/// RFC 9110, section 7.6.1, and the proxy credentials of section 11.7, which
+/// are meant for this proxy rather than the upstream server or client.
+const HOP_BY_HOP_HEADERS: &[&str] = &[
+    "connection",
+    "keep-alive",
+    "proxy-connection",
+    "te",
+    "transfer-encoding",
+    "upgrade",
+    "proxy-authenticate",
+    "proxy-authorization",
+];
+
+#[derive(Clone, Debug)]
+pub struct ProxyOptions {
+    /// Transport timeouts for the upstream request.
+    pub request_options: Option<RequestOptions>,
+    /// The client's address, for `X-Forwarded-For` and `Forwarded`. WASI
+    /// doesn't expose it, but some hosts pass it in a header.
+    pub client_addr: Option<String>,
+    /// How this proxy identifies itself in `Via`.
+    pub pseudonym: String,
+}
+
+impl Default for ProxyOptions {
+    fn default() -> Self {
+        Self {
+            request_options: None,
+            client_addr: None,
+            pseudonym: "wasi-hyperium".into(),
+        }
+    }
+}
+
+/// Forwards `request` to `upstream` and writes the upstream response to
+/// `response_out`.
+///
+/// The upstream request goes to the scheme and authority of `upstream`, with
+/// its path prefixed to the request's path. Hop-by-hop headers are stripped
+/// in both directions and `Forwarded`, `X-Forwarded-For` and `Via` are added.
+/// Bodies and trailers are streamed in both directions at once. If no
+/// response has been set when forwarding fails, `response_out` is set to the
+/// error.
+pub fn forward<Upstream, Request, Outparam, Registry>(
+    request: Request,
+    response_out: Outparam,
+    upstream: &http1::Uri,
+    options: &ProxyOptions,
+    registry: Registry,
+) -> Result<(), Error>
+where
+    Request: WasiIncomingRequest,
+    Request::IncomingBody: WasiIncomingBody<Pollable = Registry::Pollable>,
+    Upstream: WasiOutgoingHandler,
+    <Upstream::OutgoingBody as WasiOutgoingBody>::OutputStream: WasiOutputStream<
+        InputStream = <Request::IncomingBody as WasiIncomingBody>::InputStream,
+        Pollable = Registry::Pollable,
+    >,
+    Upstream::FutureIncomingResponse: WasiFutureIncomingResponse<Pollable = Registry::Pollable>,
+    UpstreamBody<Upstream>: WasiIncomingBody<Pollable = Registry::Pollable>,
+    Outparam: WasiResponseOutparam,
+    <<Outparam::OutgoingResponse as WasiOutgoingResponse>::OutgoingBody as WasiOutgoingBody>::OutputStream:
+        WasiOutputStream<
+            InputStream = <UpstreamBody<Upstream> as WasiIncomingBody>::InputStream,
+            Pollable = Registry::Pollable,
+        >,
+    Registry: PollableRegistry,
+{
+    let mut response_out = Some(ResponseOutparam::new(response_out));
+    let result = registry
+        .block_on(async {
+            let incoming = IncomingRequest::new(request, registry.clone())?;
+            let outgoing =
+                upstream_request::<Upstream, _, _>(&incoming, upstream, options, &registry)?;
+            let (request_body, future_response) = outgoing
+                .send(options.request_options.as_ref())?
+                .into_parts();
+
+            let upload = SpliceCopier::new(incoming.into_body(), request_body).copy_all();
+            let download = async {
+                let response = future_response.await?;
+                let headers = downstream_headers(response.headers(), options)?;
+                let mut outgoing = OutgoingResponse::from_headers(&headers, registry.clone())?;
+                outgoing.set_status_code(response.status())?;
+                let dest = response_out.take().unwrap().set_response(outgoing);
+                SpliceCopier::new(response.into_body(), dest)
+                    .copy_all()
+                    .await
+            };
+            try_join(upload, download).await
+        })
+        .unwrap_or_else(|stalled| Err(stalled.into()));
+    if let (Err(err), Some(response_out)) = (&result, response_out) {
+        response_out.set_error(to_error_code(err));
+    }
+    result
+}
+
+/// Like [`forward`], but for a request already converted to [`http1`] types,
+/// such as one passed to a service run by [`handle_service_call`]. It
+/// doesn't block, so it can be awaited inside such a service, and resolves to
+/// the upstream response once the request body has been uploaded. Unlike with
+/// [`forward`], bodies pass through guest memory.
+///
+/// [`handle_service_call`]: crate::hyperium1::handle_service_call
+pub async fn forward_request<HttpBody, Registry>(
+    request: http1::Request<HttpBody>,
+    upstream: &http1::Uri,
+    options: &ProxyOptions,
+    registry: Registry,
+) -> Result<
+    http1::Response<IncomingHttpBody<IncomingResponseBody<WasiRequest<Registry>>, Registry>>,
+    Error,
+>
+where
+    HttpBody: http_body1::Body + Unpin,
+    HttpBody::Data: Unpin,
+    anyhow::Error: From<HttpBody::Error>,
+    Registry: PollableRegistry,
+    Registry::Pollable: WasiHttpBindings,
+    <<WasiRequest<Registry> as WasiOutgoingRequest>::OutgoingBody as WasiOutgoingBody>::OutputStream:
+        WasiOutputStream<Pollable = Registry::Pollable>,
+    <WasiRequest<Registry> as WasiOutgoingHandler>::FutureIncomingResponse:
+        WasiFutureIncomingResponse<Pollable = Registry::Pollable>,
+    IncomingResponseBody<WasiRequest<Registry>>: WasiIncomingBody<Pollable = Registry::Pollable>,
+{
+    let (mut parts, body) = request.into_parts();
+    let authority = match parts.uri.authority() {
+        Some(authority) => Some(authority.to_string()),
+        None => parts
+            .headers
+            .get(http1::header::HOST)
+            .and_then(|host| host.to_str().ok())
+            .map(Into::into),
+    };
+    let scheme = parts.uri.scheme().map(Scheme::from);
+    let headers = upstream_headers((&parts.headers).into(), authority, scheme, options)?;
+    parts.headers = headers.try_into()?;
+
+    let path_and_query = parts.uri.path_and_query().map(|path| path.as_str());
+    let mut uri = http1::Uri::builder().path_and_query(upstream_path(upstream, path_and_query));
+    if let Some(scheme) = upstream.scheme() {
+        uri = uri.scheme(scheme.clone());
+    }
+    if let Some(authority) = upstream.authority() {
+        uri = uri.authority(authority.clone());
+    }
+    parts.uri = uri.build()?;
+    if let Some(request_options) = options.request_options {
+        parts.extensions.insert(request_options);
+    }
+
+    let request = http1::Request::from_parts(parts, body);
+    let (response, upload) = start::<WasiRequest<Registry>, _, _>(request, registry)?;
+    let mut response = RequestFuture::new(response, upload).await?;
+    let headers = downstream_headers(response.headers().into(), options)?;
+    *response.headers_mut() = headers.try_into()?;
+    Ok(response)
+}
+
+type UpstreamBody<Upstream> = <<<Upstream as WasiOutgoingHandler>::FutureIncomingResponse as WasiFutureIncomingResponse>::IncomingResponse as WasiIncomingResponse>::IncomingBody;
+
+fn upstream_request<Upstream, Request, Registry>(
+    incoming: &IncomingRequest<Request, Registry>,
+    upstream: &http1::Uri,
+    options: &ProxyOptions,
+    registry: &Registry,
+) -> Result<OutgoingRequest<Upstream, Registry>, Error>
+where
+    Request: WasiIncomingRequest,
+    Request::IncomingBody: WasiIncomingBody<Pollable = Registry::Pollable>,
+    Upstream: WasiOutgoingHandler,
+    <Upstream::OutgoingBody as WasiOutgoingBody>::OutputStream:
+        WasiOutputStream<Pollable = Registry::Pollable>,
+    Registry: PollableRegistry,
+{
+    let headers = upstream_headers(
+        incoming.headers(),
+        incoming.authority(),
+        incoming.scheme(),
+        options,
+    )?;
+    let mut outgoing = OutgoingRequest::from_headers(&headers, registry.clone())?;
+    outgoing.set_method(incoming.method())?;
+    if let Some(scheme) = upstream.scheme() {
+        outgoing.set_scheme(Some(scheme.into()))?;
+    }
+    if let Some(authority) = upstream.authority() {
+        outgoing.set_authority(Some(authority.as_str()))?;
+    }
+    let path_with_query = upstream_path(upstream, incoming.path_with_query().as_deref());
+    outgoing.set_path_with_query(Some(&path_with_query))?;
+    Ok(outgoing)
+}
+
+/// Strips hop-by-hop headers from a request's headers and adds `Forwarded`,
+/// `X-Forwarded-For` and `Via`.
+fn upstream_headers(
+    headers: FieldEntries,
+    authority: Option<String>,
+    scheme: Option<Scheme>,
+    options: &ProxyOptions,
+) -> Result<FieldEntries, Error> {
+    let mut headers = strip_hop_by_hop(headers);
+    let client = options.client_addr.as_deref();
+    if let Some(client) = client {
+        headers.push(("x-forwarded-for".into(), client.into()));
+    }
+    let mut forwarded = format!("for={}", forwarded_value(client.unwrap_or("unknown")));
+    if let Some(authority) = authority {
+        forwarded.push_str(&format!(";host={}", forwarded_value(&authority)));
+    }
+    if let Some(scheme) = scheme {
+        let proto = match scheme {
+            Scheme::Http => "http".into(),
+            Scheme::Https => "https".into(),
+            Scheme::Other(other) => other,
+        };
+        forwarded.push_str(&format!(";proto={}", forwarded_value(&proto)));
+    }
+    headers.push(("forwarded".into(), forwarded.into_bytes()));
+    headers.push(("via".into(), via(options).into_bytes()));
+    let mut headers = FieldEntries::from(headers);
+    headers.sanitize(ForbiddenHeaderPolicy::Strip)?;
+    Ok(headers)
+}
+
+/// Strips hop-by-hop headers from a response's headers and adds `Via`.
+fn downstream_headers(
+    headers: FieldEntries,
+    options: &ProxyOptions,
+) -> Result<FieldEntries, Error> {
+    let mut headers = strip_hop_by_hop(headers);
+    headers.push(("via".into(), via(options).into_bytes()));
+    let mut headers = FieldEntries::from(headers);
+    headers.sanitize(ForbiddenHeaderPolicy::Strip)?;
+    Ok(headers)
+}
+
+/// Prefixes the path of `upstream` to a request's path.
+fn upstream_path(upstream: &http1::Uri, path_with_query: Option<&str>) -> String {
+    let prefix = upstream.path().trim_end_matches('/');
+    format!("{prefix}{}", path_with_query.unwrap_or("/"))
+}
+
+/// Removes hop-by-hop headers, including any named by `Connection`.
+fn strip_hop_by_hop(headers: FieldEntries) -> Vec<(String, Vec<u8>)> {
+    let headers = headers.into_iter().collect::<Vec<_>>();
+    let mut hop_by_hop = HOP_BY_HOP_HEADERS
+        .iter()
+        .map(|name| name.to_string())
+        .collect::<Vec<_>>();
+    for (name, value) in &headers {
+        if name.eq_ignore_ascii_case("connection") {
+            let value = String::from_utf8_lossy(value);
+            hop_by_hop.extend(value.split(',').map(|name| name.trim().to_string()));
+        }
+    }
+    headers
+        .into_iter()
+        .filter(|(name, _)| !hop_by_hop.iter().any(|hop| hop.eq_ignore_ascii_case(name)))
+        .collect()
+}
+
+fn via(options: &ProxyOptions) -> String {
+    format!("1.1 {}", options.pseudonym)
+}
+
+/// Quotes `value` for `Forwarded` unless it is a token (RFC 7239).
+fn forwarded_value(value: &str) -> String {
+    let is_token = !value.is_empty()
+        && value
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&b));
+    if is_token {
+        value.into()
+    } else {
+        format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+    }
+}
+
+fn to_error_code(err: &Error) -> ErrorCode {
+    match err {
+        Error::WasiErrorCode(code) => code.clone(),
+        err => ErrorCode::InternalError(Some(err.to_string())),
+    }
+}
+
+/// Polls both futures until both complete or either fails.
+async fn try_join(
+    a: impl Future<Output = Result<(), Error>>,
+    b: impl Future<Output = Result<(), Error>>,
+) -> Result<(), Error> {
+    let mut a = pin!(a);
+    let mut b = pin!(b);
+    let (mut a_done, mut b_done) = (false, false);
+    poll_fn(|cx| {
+        if !a_done {
+            if let Poll::Ready(res) = a.as_mut().poll(cx) {
+                res?;
+                a_done = true;
+            }
+        }
+        if !b_done {
+            if let Poll::Ready(res) = b.as_mut().poll(cx) {
+                res?;
+                b_done = true;
+            }
+        }
+        if a_done && b_done {
+            Poll::Ready(Ok(()))
+        } else {
+            Poll::Pending
+        }
+    })
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, rc::Rc};
+
+    use bytes::Bytes;
+    use http_body_util::{BodyExt, Full};
+
+    use crate::{
+        poll::{PollableRegistry, Poller},
+        testing::{
+            MockFields, MockFutureIncomingResponse, MockFutureTrailers, MockIncomingBody,
+            MockIncomingRequest, MockIncomingResponse, MockInputStream, MockOutgoingHandler,
+            MockOutgoingRequest, MockPollable, MockResponseOutparam,
+        },
+        wasi::{
+            traits::{WasiFields, WasiOutgoingRequest, WasiOutgoingResponse},
+            ErrorCode, Method, Scheme,
+        },
+        Error,
+    };
+
+    use super::{forward, forward_request, ProxyOptions};
+
+    fn entries(entries: &[(&str, &str)]) -> Vec<(String, Vec<u8>)> {
+        entries
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.as_bytes().to_vec()))
+            .collect()
+    }
+
+    #[test]
+    fn forwards_request_and_response_with_trailers() {
+        let upstream_body = Rc::new(RefCell::new(None));
+        let _handler = MockOutgoingHandler::install({
+            let upstream_body = upstream_body.clone();
+            move |request| {
+                assert_eq!(request.method(), Method::Post);
+                assert_eq!(request.scheme(), Some(Scheme::Https));
+                assert_eq!(request.authority().as_deref(), Some("upstream:8443"));
+                assert_eq!(request.path_with_query().as_deref(), Some("/api/items?x=1"));
+                assert_eq!(
+                    request.headers().entries(),
+                    entries(&[
+                        ("accept", "*/*"),
+                        ("x-forwarded-for", "10.0.0.1"),
+                        ("forwarded", "for=10.0.0.1;host=\"proxy:80\";proto=http"),
+                        ("via", "1.1 wasi-hyperium"),
+                    ])
+                );
+
+                let trailers = MockFields::from(entries(&[("x-checksum", "abc")]));
+                let body = MockIncomingBody::new(
+                    MockInputStream::with_data("pong"),
+                    MockFutureTrailers::ready(Some(trailers)),
+                );
+                let response = MockIncomingResponse::new(201, body).with_headers(entries(&[
+                    ("content-type", "text/plain"),
+                    ("connection", "close"),
+                ]));
+                *upstream_body.borrow_mut() = Some(request.outgoing_body());
+                Ok(MockFutureIncomingResponse::ready(Ok(response)))
+            }
+        });
+
+        let request = MockIncomingRequest::new(
+            Method::Post,
+            Some("/items?x=1"),
+            MockIncomingBody::with_data("ping"),
+        )
+        .with_scheme(Scheme::Http)
+        .with_authority("proxy:80")
+        .with_headers(entries(&[
+            ("host", "proxy:80"),
+            ("connection", "x-hop"),
+            ("x-hop", "1"),
+            ("keep-alive", "timeout=5"),
+            ("accept", "*/*"),
+        ]));
+        let outparam = MockResponseOutparam::new();
+        let options = ProxyOptions {
+            client_addr: Some("10.0.0.1".into()),
+            ..Default::default()
+        };
+        let upstream = "https://upstream:8443/api/".parse().unwrap();
+        forward::<MockOutgoingRequest, _, _, _>(
+            request,
+            outparam.clone(),
+            &upstream,
+            &options,
+            Poller::<MockPollable>::default(),
+        )
+        .unwrap();
+
+        let response = outparam.take_response().unwrap().unwrap();
+        assert_eq!(response.status_code(), 201);
+        assert_eq!(
+            response.headers().entries(),
+            entries(&[("content-type", "text/plain"), ("via", "1.1 wasi-hyperium")])
+        );
+        let upstream_body = upstream_body.take().unwrap();
+        assert_eq!(upstream_body.written(), b"ping");
+        assert!(upstream_body.is_finished());
+        let body = response.outgoing_body();
+        assert_eq!(body.written(), b"pong");
+        assert_eq!(body.trailers(), Some(entries(&[("x-checksum", "abc")])));
+    }
+
+    #[test]
+    fn sets_error_when_upstream_fails() {
+        let _handler = MockOutgoingHandler::install(|_| {
+            Ok(MockFutureIncomingResponse::ready(Err(
+                ErrorCode::ConnectionRefused,
+            )))
+        });
+
+        let request = MockIncomingRequest::new(Method::Get, None, MockIncomingBody::with_data(""));
+        let outparam = MockResponseOutparam::new();
+        let upstream = "http://upstream".parse().unwrap();
+        let err = forward::<MockOutgoingRequest, _, _, _>(
+            request,
+            outparam.clone(),
+            &upstream,
+            &ProxyOptions::default(),
+            Poller::<MockPollable>::default(),
+        )
+        .unwrap_err();
+        assert!(matches!(
+            err,
+            Error::WasiErrorCode(ErrorCode::ConnectionRefused)
+        ));
+        assert!(matches!(
+            outparam.take_response(),
+            Some(Err(ErrorCode::ConnectionRefused))
+        ));
+    }
+
+    #[test]
+    fn forward_request_rewrites_headers_both_ways() {
+        let _handler = MockOutgoingHandler::install(|request| {
+            assert_eq!(request.scheme(), Some(Scheme::Http));
+            assert_eq!(request.authority().as_deref(), Some("upstream"));
+            assert_eq!(request.path_with_query().as_deref(), Some("/v1/items"));
+            assert_eq!(
+                request.headers().entries(),
+                entries(&[
+                    ("trailer", "x-checksum"),
+                    ("forwarded", "for=unknown;host=proxy;proto=http"),
+                    ("via", "1.1 wasi-hyperium"),
+                ])
+            );
+            let body = MockIncomingBody::with_data("pong");
+            let response = MockIncomingResponse::new(200, body).with_headers(entries(&[
+                ("connection", "close"),
+                ("content-type", "text/plain"),
+            ]));
+            Ok(MockFutureIncomingResponse::ready(Ok(response)))
+        });
+
+        let request = http1::Request::get("http://proxy/items")
+            .header("connection", "close")
+            .header("trailer", "x-checksum")
+            .body(Full::new(Bytes::new()))
+            .unwrap();
+        let upstream = "http://upstream/v1".parse().unwrap();
+        let registry = Poller::<MockPollable>::default();
+        let options = ProxyOptions::default();
+        let (headers, body) = registry
+            .block_on(async {
+                let response =
+                    forward_request(request, &upstream, &options, registry.clone()).await?;
+                let (parts, body) = response.into_parts();
+                Ok::<_, Error>((parts.headers, body.collect().await?.to_bytes()))
+            })
+            .unwrap()
+            .unwrap();
+        assert_eq!(headers.len(), 2);
+        assert_eq!(headers["content-type"], "text/plain");
+        assert_eq!(headers["via"], "1.1 wasi-hyperium");
+        assert_eq!(body, "pong");
+    }
+
+    #[test]
+    fn forward_request_keeps_query() {
+        let _handler = MockOutgoingHandler::install(|request| {
+            assert_eq!(request.path_with_query().as_deref(), Some("/v1/items?a=b"));
+            let body = MockIncomingBody::with_data("");
+            Ok(MockFutureIncomingResponse::ready(Ok(
+                MockIncomingResponse::new(200, body),
+            )))
+        });
+
+        let request = http1::Request::get("http://proxy/items?a=b")
+            .body(Full::new(Bytes::new()))
+            .unwrap();
+        let upstream = "http://upstream/v1".parse().unwrap();
+        let registry = Poller::<MockPollable>::default();
+        let options = ProxyOptions::default();
+        let status = registry
+            .block_on(async {
+                let response =
+                    forward_request(request, &upstream, &options, registry.clone()).await?;
+                Ok::<_, Error>(response.status())
+            })
+            .unwrap()
+            .unwrap();
+        assert_eq!(status, 200);
+    }
+}
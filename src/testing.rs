@@ -0,0 +1,226 @@
+//! An in-memory WASI host for running this crate natively, e.g. in `cargo test`.
+//!
+//! The mock types implement the traits in [`crate::wasi::traits`] with
+//! [`MockPollable`] as their pollable. Their state is shared between clones,
+//! so a test can keep a handle to a stream or future to script it while the
+//! crate owns another.
+//!
+//! When [`WasiPoll::poll`] finds nothing ready, the mock host advances its
+//! monotonic clock to the next event scheduled with [`schedule`] or the next
+//! timer being polled, whichever is earlier, and runs any events that are due.
+//! If there is neither, the poll would block forever, so it panics.
+
+use std::{cell::RefCell, rc::Rc, time::Duration};
+
+use crate::wasi::traits::{WasiMonotonicClock, WasiPoll, WasiPollable};
+
+mod http;
+mod io;
+
+pub use http::{
+    MockFields, MockFutureIncomingResponse, MockFutureTrailers, MockHeaderError, MockIncomingBody,
+    MockIncomingRequest, MockIncomingResponse, MockOutgoingBody, MockOutgoingHandler,
+    MockOutgoingRequest, MockOutgoingResponse, MockRequestOptions, MockResponseOutparam,
+};
+pub use io::{MockInputStream, MockIoError, MockOutputStream, MockStreamError};
+
+thread_local! {
+    static HOST: RefCell<Host> = RefCell::new(Host::default());
+}
+
+#[derive(Default)]
+struct Host {
+    now: u64,
+    next_handle: u32,
+    next_seq: u64,
+    events: Vec<Event>,
+}
+
+struct Event {
+    at: u64,
+    seq: u64,
+    run: Box<dyn FnOnce()>,
+}
+
+/// Schedules `f` to run on this thread's mock host once its monotonic clock
+/// has advanced by `delay`. Events run in order of time and then of
+/// scheduling, either from [`advance`] or when a poll finds nothing ready.
+pub fn schedule(delay: Duration, f: impl FnOnce() + 'static) {
+    HOST.with(|host| {
+        let mut host = host.borrow_mut();
+        let event = Event {
+            at: host.now.saturating_add(duration_nanos(delay)),
+            seq: host.next_seq,
+            run: Box::new(f),
+        };
+        host.next_seq += 1;
+        host.events.push(event);
+    })
+}
+
+/// Advances the mock monotonic clock by `duration`, running any events that
+/// fall due.
+pub fn advance(duration: Duration) {
+    let target = now().saturating_add(duration_nanos(duration));
+    run_until(target);
+}
+
+/// Returns the current time of the mock monotonic clock, in nanoseconds.
+pub fn now() -> u64 {
+    HOST.with(|host| host.borrow().now)
+}
+
+fn run_until(target: u64) {
+    HOST.with(|host| {
+        let mut host = host.borrow_mut();
+        host.now = host.now.max(target);
+    });
+    // NOTE: the host must not be borrowed while an event runs; it may
+    // schedule others
+    while let Some(event) = next_due_event() {
+        (event.run)();
+    }
+}
+
+fn next_due_event() -> Option<Event> {
+    HOST.with(|host| {
+        let mut host = host.borrow_mut();
+        let now = host.now;
+        let idx = host
+            .events
+            .iter()
+            .enumerate()
+            .filter(|(_, event)| event.at <= now)
+            .min_by_key(|(_, event)| (event.at, event.seq))
+            .map(|(idx, _)| idx)?;
+        Some(host.events.remove(idx))
+    })
+}
+
+/// Advances the host to its next event or `next_timer`. Returns false if
+/// there is neither.
+fn step(next_timer: Option<u64>) -> bool {
+    let next_event = HOST.with(|host| host.borrow().events.iter().map(|event| event.at).min());
+    let target = match (next_event, next_timer) {
+        (Some(event), Some(timer)) => event.min(timer),
+        (Some(at), None) | (None, Some(at)) => at,
+        (None, None) => return false,
+    };
+    run_until(target);
+    true
+}
+
+fn next_handle() -> u32 {
+    HOST.with(|host| {
+        let mut host = host.borrow_mut();
+        host.next_handle += 1;
+        host.next_handle
+    })
+}
+
+/// A pollable whose readiness is either computed by a closure over some mock
+/// state or set by the mock monotonic clock.
+pub struct MockPollable {
+    handle: u32,
+    readiness: Readiness,
+}
+
+enum Readiness {
+    Check(Rc<dyn Fn() -> bool>),
+    Instant(u64),
+}
+
+impl MockPollable {
+    /// Creates a pollable which is ready whenever `ready` returns true.
+    pub fn new(ready: impl Fn() -> bool + 'static) -> Self {
+        Self {
+            handle: next_handle(),
+            readiness: Readiness::Check(Rc::new(ready)),
+        }
+    }
+
+    pub fn always_ready() -> Self {
+        Self::new(|| true)
+    }
+
+    fn deadline(&self) -> Option<u64> {
+        match self.readiness {
+            Readiness::Check(_) => None,
+            Readiness::Instant(when) => Some(when),
+        }
+    }
+}
+
+impl WasiPollable for MockPollable {
+    fn handle(&self) -> u32 {
+        self.handle
+    }
+
+    fn ready(&self) -> bool {
+        match &self.readiness {
+            Readiness::Check(ready) => ready(),
+            Readiness::Instant(when) => now() >= *when,
+        }
+    }
+}
+
+impl WasiPoll for MockPollable {
+    fn poll(pollables: &[&Self]) -> Vec<u32> {
+        assert!(!pollables.is_empty(), "poll called with no pollables");
+        loop {
+            let ready = pollables
+                .iter()
+                .enumerate()
+                .filter(|(_, pollable)| pollable.ready())
+                .map(|(idx, _)| idx as u32)
+                .collect::<Vec<_>>();
+            if !ready.is_empty() {
+                return ready;
+            }
+            let next_timer = pollables.iter().filter_map(|p| p.deadline()).min();
+            if !step(next_timer) {
+                panic!(
+                    "poll would block forever: nothing is ready and no host events are scheduled"
+                );
+            }
+        }
+    }
+}
+
+impl WasiMonotonicClock for MockPollable {
+    fn now() -> u64 {
+        now()
+    }
+
+    fn resolution() -> u64 {
+        1
+    }
+
+    fn subscribe_instant(when: u64) -> Self {
+        Self {
+            handle: next_handle(),
+            readiness: Readiness::Instant(when),
+        }
+    }
+
+    fn subscribe_duration(when: u64) -> Self {
+        Self::subscribe_instant(now().saturating_add(when))
+    }
+}
+
+fn duration_nanos(duration: Duration) -> u64 {
+    duration.as_nanos().try_into().unwrap_or(u64::MAX)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MockPollable;
+
+    #[test]
+    #[should_panic(expected = "block forever")]
+    fn poll_panics_when_nothing_can_become_ready() {
+        use crate::wasi::traits::WasiPoll;
+        let pollable = MockPollable::new(|| false);
+        MockPollable::poll(&[&pollable]);
+    }
+}
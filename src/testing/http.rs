@@ -0,0 +1,810 @@
+use std::{
+    cell::{Cell, RefCell},
+    fmt,
+    rc::Rc,
+    time::Duration,
+};
+
+use crate::wasi::{
+    is_forbidden_header,
+    traits::{
+        WasiErrorCode, WasiFields, WasiFutureIncomingResponse, WasiFutureTrailers, WasiHeaderError,
+        WasiHttpBindings, WasiIncomingBody, WasiIncomingRequest, WasiIncomingResponse, WasiMethod,
+        WasiOutgoingBody, WasiOutgoingHandler, WasiOutgoingRequest, WasiOutgoingResponse,
+        WasiRequestOptions, WasiResponseOutparam, WasiScheme, WasiSubscribe,
+    },
+    ErrorCode, FieldsErrorKind, Method, Scheme,
+};
+
+use super::{MockInputStream, MockOutputStream, MockPollable};
+
+type Entries = Vec<(String, Vec<u8>)>;
+type TrailersResult = Result<Option<MockFields>, ErrorCode>;
+
+impl WasiErrorCode for ErrorCode {
+    fn to_error_code(&self) -> ErrorCode {
+        self.clone()
+    }
+
+    fn from_error_code(code: ErrorCode) -> Self {
+        code
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MockHeaderError {
+    InvalidSyntax,
+    Forbidden,
+    Immutable,
+}
+
+impl fmt::Display for MockHeaderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let msg = match self {
+            Self::InvalidSyntax => "invalid header syntax",
+            Self::Forbidden => "forbidden header",
+            Self::Immutable => "immutable headers",
+        };
+        f.write_str(msg)
+    }
+}
+
+impl std::error::Error for MockHeaderError {}
+
+impl WasiHeaderError for MockHeaderError {
+    fn kind(&self) -> FieldsErrorKind {
+        match self {
+            Self::InvalidSyntax => FieldsErrorKind::InvalidSyntax,
+            Self::Forbidden => FieldsErrorKind::Forbidden,
+            Self::Immutable => FieldsErrorKind::Immutable,
+        }
+    }
+}
+
+/// Headers or trailers, validated like a host validates fields from a guest.
+/// Fields converted from a list of entries with `From` are not validated, as
+/// for fields which come from the host.
+#[derive(Debug, Default)]
+pub struct MockFields {
+    entries: Rc<RefCell<Entries>>,
+    immutable: bool,
+}
+
+impl MockFields {
+    /// Returns an immutable handle to the same fields, as hosts hand out for
+    /// the headers of requests and responses.
+    pub fn to_immutable(&self) -> Self {
+        Self {
+            entries: self.entries.clone(),
+            immutable: true,
+        }
+    }
+
+    pub fn is_immutable(&self) -> bool {
+        self.immutable
+    }
+
+    fn validate_name(name: &str) -> Result<(), MockHeaderError> {
+        let valid = !name.is_empty()
+            && name
+                .bytes()
+                .all(|b| b.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&b));
+        if !valid {
+            return Err(MockHeaderError::InvalidSyntax);
+        }
+        if is_forbidden_header(name) {
+            return Err(MockHeaderError::Forbidden);
+        }
+        Ok(())
+    }
+
+    fn validate(name: &str, value: &[u8]) -> Result<(), MockHeaderError> {
+        if value.iter().any(|b| matches!(b, b'\r' | b'\n' | b'\0')) {
+            return Err(MockHeaderError::InvalidSyntax);
+        }
+        Self::validate_name(name)
+    }
+
+    fn check_mutable(&self) -> Result<(), MockHeaderError> {
+        if self.immutable {
+            return Err(MockHeaderError::Immutable);
+        }
+        Ok(())
+    }
+}
+
+impl From<Entries> for MockFields {
+    fn from(entries: Entries) -> Self {
+        Self {
+            entries: Rc::new(RefCell::new(entries)),
+            immutable: false,
+        }
+    }
+}
+
+impl WasiFields for MockFields {
+    type Error = MockHeaderError;
+
+    fn new() -> Self {
+        Self::default()
+    }
+
+    fn from_list(entries: &[(String, Vec<u8>)]) -> Result<Self, Self::Error> {
+        for (name, value) in entries {
+            Self::validate(name, value)?;
+        }
+        Ok(entries.to_vec().into())
+    }
+
+    fn get(&self, name: &str) -> Vec<Vec<u8>> {
+        self.entries
+            .borrow()
+            .iter()
+            .filter(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.clone())
+            .collect()
+    }
+
+    fn has(&self, name: &str) -> bool {
+        self.entries
+            .borrow()
+            .iter()
+            .any(|(key, _)| key.eq_ignore_ascii_case(name))
+    }
+
+    fn set(&self, name: &str, values: &[Vec<u8>]) -> Result<(), Self::Error> {
+        self.check_mutable()?;
+        Self::validate_name(name)?;
+        for value in values {
+            Self::validate(name, value)?;
+        }
+        let mut entries = self.entries.borrow_mut();
+        entries.retain(|(key, _)| !key.eq_ignore_ascii_case(name));
+        entries.extend(values.iter().map(|value| (name.to_string(), value.clone())));
+        Ok(())
+    }
+
+    fn append(&self, name: &str, value: &[u8]) -> Result<(), Self::Error> {
+        self.check_mutable()?;
+        Self::validate(name, value)?;
+        self.entries
+            .borrow_mut()
+            .push((name.to_string(), value.to_vec()));
+        Ok(())
+    }
+
+    fn delete(&self, name: &str) -> Result<(), Self::Error> {
+        self.check_mutable()?;
+        Self::validate_name(name)?;
+        self.entries
+            .borrow_mut()
+            .retain(|(key, _)| !key.eq_ignore_ascii_case(name));
+        Ok(())
+    }
+
+    fn entries(&self) -> Vec<(String, Vec<u8>)> {
+        self.entries.borrow().clone()
+    }
+
+    fn clone(&self) -> Self {
+        self.entries().into()
+    }
+}
+
+impl WasiMethod for Method {
+    fn from_method(method: Method) -> Self {
+        method
+    }
+
+    fn into_method(self) -> Method {
+        self
+    }
+}
+
+impl WasiScheme for Scheme {
+    fn from_scheme(scheme: Scheme) -> Self {
+        scheme
+    }
+
+    fn into_scheme(self) -> Scheme {
+        self
+    }
+}
+
+/// Trailers which arrive when resolved.
+#[derive(Clone, Default)]
+pub struct MockFutureTrailers {
+    state: Rc<RefCell<TrailersState>>,
+}
+
+#[derive(Default)]
+enum TrailersState {
+    #[default]
+    Pending,
+    Ready(TrailersResult),
+    Taken,
+}
+
+impl MockFutureTrailers {
+    /// Creates trailers which are pending until resolved.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn ready(trailers: Option<MockFields>) -> Self {
+        let future = Self::new();
+        future.resolve(Ok(trailers));
+        future
+    }
+
+    pub fn resolve(&self, result: TrailersResult) {
+        *self.state.borrow_mut() = TrailersState::Ready(result);
+    }
+}
+
+impl WasiSubscribe for MockFutureTrailers {
+    type Pollable = MockPollable;
+
+    fn subscribe(&self) -> Self::Pollable {
+        let state = self.state.clone();
+        MockPollable::new(move || !matches!(*state.borrow(), TrailersState::Pending))
+    }
+}
+
+impl WasiFutureTrailers for MockFutureTrailers {
+    type Trailers = MockFields;
+    type ErrorCode = ErrorCode;
+
+    fn get(&self) -> Option<Result<Result<Option<Self::Trailers>, Self::ErrorCode>, ()>> {
+        let mut state = self.state.borrow_mut();
+        match std::mem::replace(&mut *state, TrailersState::Taken) {
+            TrailersState::Pending => {
+                *state = TrailersState::Pending;
+                None
+            }
+            TrailersState::Ready(result) => Some(Ok(result)),
+            TrailersState::Taken => Some(Err(())),
+        }
+    }
+}
+
+pub struct MockIncomingBody {
+    stream: RefCell<Option<MockInputStream>>,
+    trailers: MockFutureTrailers,
+}
+
+impl MockIncomingBody {
+    pub fn new(stream: MockInputStream, trailers: MockFutureTrailers) -> Self {
+        Self {
+            stream: RefCell::new(Some(stream)),
+            trailers,
+        }
+    }
+
+    /// Creates a body of `data` without trailers.
+    pub fn with_data(data: impl AsRef<[u8]>) -> Self {
+        Self::new(
+            MockInputStream::with_data(data),
+            MockFutureTrailers::ready(None),
+        )
+    }
+}
+
+impl WasiIncomingBody for MockIncomingBody {
+    type Pollable = MockPollable;
+    type InputStream = MockInputStream;
+    type FutureTrailers = MockFutureTrailers;
+
+    fn stream(&self) -> Result<Self::InputStream, ()> {
+        self.stream.borrow_mut().take().ok_or(())
+    }
+
+    fn finish(self) -> Self::FutureTrailers {
+        self.trailers
+    }
+}
+
+pub struct MockIncomingRequest {
+    method: Method,
+    path_with_query: Option<String>,
+    scheme: Option<Scheme>,
+    authority: Option<String>,
+    headers: MockFields,
+    body: RefCell<Option<MockIncomingBody>>,
+}
+
+impl MockIncomingRequest {
+    pub fn new(method: Method, path_with_query: Option<&str>, body: MockIncomingBody) -> Self {
+        Self {
+            method,
+            path_with_query: path_with_query.map(Into::into),
+            scheme: None,
+            authority: None,
+            headers: Default::default(),
+            body: RefCell::new(Some(body)),
+        }
+    }
+
+    pub fn with_scheme(mut self, scheme: Scheme) -> Self {
+        self.scheme = Some(scheme);
+        self
+    }
+
+    pub fn with_authority(mut self, authority: &str) -> Self {
+        self.authority = Some(authority.into());
+        self
+    }
+
+    pub fn with_headers(mut self, headers: Entries) -> Self {
+        self.headers = headers.into();
+        self
+    }
+}
+
+impl WasiIncomingRequest for MockIncomingRequest {
+    type Method = Method;
+    type Scheme = Scheme;
+    type Headers = MockFields;
+    type IncomingBody = MockIncomingBody;
+
+    fn method(&self) -> Self::Method {
+        self.method.clone()
+    }
+
+    fn path_with_query(&self) -> Option<String> {
+        self.path_with_query.clone()
+    }
+
+    fn scheme(&self) -> Option<Self::Scheme> {
+        self.scheme.clone()
+    }
+
+    fn authority(&self) -> Option<String> {
+        self.authority.clone()
+    }
+
+    fn headers(&self) -> Self::Headers {
+        self.headers.to_immutable()
+    }
+
+    fn consume(&self) -> Result<Self::IncomingBody, ()> {
+        self.body.borrow_mut().take().ok_or(())
+    }
+}
+
+pub struct MockIncomingResponse {
+    status: u16,
+    headers: MockFields,
+    body: RefCell<Option<MockIncomingBody>>,
+}
+
+impl MockIncomingResponse {
+    pub fn new(status: u16, body: MockIncomingBody) -> Self {
+        Self {
+            status,
+            headers: Default::default(),
+            body: RefCell::new(Some(body)),
+        }
+    }
+
+    pub fn with_headers(mut self, headers: Entries) -> Self {
+        self.headers = headers.into();
+        self
+    }
+}
+
+impl WasiIncomingResponse for MockIncomingResponse {
+    type Headers = MockFields;
+    type IncomingBody = MockIncomingBody;
+
+    fn status(&self) -> u16 {
+        self.status
+    }
+
+    fn headers(&self) -> Self::Headers {
+        self.headers.to_immutable()
+    }
+
+    fn consume(&self) -> Result<Self::IncomingBody, ()> {
+        self.body.borrow_mut().take().ok_or(())
+    }
+}
+
+/// A response which arrives when resolved.
+#[derive(Clone, Default)]
+pub struct MockFutureIncomingResponse {
+    state: Rc<RefCell<ResponseState>>,
+}
+
+#[derive(Default)]
+enum ResponseState {
+    #[default]
+    Pending,
+    Ready(Result<MockIncomingResponse, ErrorCode>),
+    Taken,
+}
+
+impl MockFutureIncomingResponse {
+    /// Creates a response which is pending until resolved.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn ready(result: Result<MockIncomingResponse, ErrorCode>) -> Self {
+        let future = Self::new();
+        future.resolve(result);
+        future
+    }
+
+    pub fn resolve(&self, result: Result<MockIncomingResponse, ErrorCode>) {
+        *self.state.borrow_mut() = ResponseState::Ready(result);
+    }
+}
+
+impl WasiSubscribe for MockFutureIncomingResponse {
+    type Pollable = MockPollable;
+
+    fn subscribe(&self) -> Self::Pollable {
+        let state = self.state.clone();
+        MockPollable::new(move || !matches!(*state.borrow(), ResponseState::Pending))
+    }
+}
+
+impl WasiFutureIncomingResponse for MockFutureIncomingResponse {
+    type IncomingResponse = MockIncomingResponse;
+    type ErrorCode = ErrorCode;
+
+    fn get(&self) -> Option<Result<Result<Self::IncomingResponse, Self::ErrorCode>, ()>> {
+        let mut state = self.state.borrow_mut();
+        match std::mem::replace(&mut *state, ResponseState::Taken) {
+            ResponseState::Pending => {
+                *state = ResponseState::Pending;
+                None
+            }
+            ResponseState::Ready(result) => Some(Ok(result)),
+            ResponseState::Taken => Some(Err(())),
+        }
+    }
+}
+
+/// An outgoing body recording what is written to it and how it is finished.
+#[derive(Clone)]
+pub struct MockOutgoingBody {
+    state: Rc<RefCell<OutgoingBodyState>>,
+}
+
+struct OutgoingBodyState {
+    stream: MockOutputStream,
+    stream_taken: bool,
+    finished: bool,
+    trailers: Option<MockFields>,
+    finish_error: Option<ErrorCode>,
+}
+
+impl MockOutgoingBody {
+    pub fn new(stream: MockOutputStream) -> Self {
+        Self {
+            state: Rc::new(RefCell::new(OutgoingBodyState {
+                stream,
+                stream_taken: false,
+                finished: false,
+                trailers: None,
+                finish_error: None,
+            })),
+        }
+    }
+
+    pub fn stream(&self) -> MockOutputStream {
+        self.state.borrow().stream.clone()
+    }
+
+    /// Returns everything written to the body so far.
+    pub fn written(&self) -> Vec<u8> {
+        self.state.borrow().stream.written()
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.state.borrow().finished
+    }
+
+    /// Returns the trailers the body was finished with, if any.
+    pub fn trailers(&self) -> Option<Vec<(String, Vec<u8>)>> {
+        self.state.borrow().trailers.as_ref().map(|t| t.entries())
+    }
+
+    /// Fails the call to finish the body with `code`.
+    pub fn fail_finish(&self, code: ErrorCode) {
+        self.state.borrow_mut().finish_error = Some(code);
+    }
+}
+
+impl Default for MockOutgoingBody {
+    fn default() -> Self {
+        Self::new(Default::default())
+    }
+}
+
+impl WasiOutgoingBody for MockOutgoingBody {
+    type OutputStream = MockOutputStream;
+    type Trailers = MockFields;
+    type ErrorCode = ErrorCode;
+
+    fn write(&self) -> Result<Self::OutputStream, ()> {
+        let mut state = self.state.borrow_mut();
+        if state.stream_taken {
+            return Err(());
+        }
+        state.stream_taken = true;
+        Ok(state.stream.clone())
+    }
+
+    fn finish(self, trailers: Option<Self::Trailers>) -> Result<(), Self::ErrorCode> {
+        let mut state = self.state.borrow_mut();
+        if let Some(err) = state.finish_error.take() {
+            return Err(err);
+        }
+        state.finished = true;
+        state.trailers = trailers;
+        Ok(())
+    }
+}
+
+pub struct MockOutgoingRequest {
+    headers: MockFields,
+    method: RefCell<Method>,
+    path_with_query: RefCell<Option<String>>,
+    scheme: RefCell<Option<Scheme>>,
+    authority: RefCell<Option<String>>,
+    body: MockOutgoingBody,
+    body_taken: Cell<bool>,
+    options: RefCell<Option<MockRequestOptions>>,
+}
+
+impl MockOutgoingRequest {
+    /// Returns a handle to the request body, for inspecting what is written.
+    pub fn outgoing_body(&self) -> MockOutgoingBody {
+        self.body.clone()
+    }
+
+    /// Returns the options the request was sent with.
+    pub fn options(&self) -> Option<MockRequestOptions> {
+        self.options.borrow().clone()
+    }
+}
+
+impl WasiOutgoingRequest for MockOutgoingRequest {
+    type Method = Method;
+    type Scheme = Scheme;
+    type Headers = MockFields;
+    type OutgoingBody = MockOutgoingBody;
+
+    fn new(headers: Self::Headers) -> Self {
+        Self {
+            headers,
+            method: RefCell::new(Method::Get),
+            path_with_query: Default::default(),
+            scheme: Default::default(),
+            authority: Default::default(),
+            body: Default::default(),
+            body_taken: Cell::new(false),
+            options: Default::default(),
+        }
+    }
+
+    fn body(&self) -> Result<Self::OutgoingBody, ()> {
+        if self.body_taken.replace(true) {
+            return Err(());
+        }
+        Ok(self.body.clone())
+    }
+
+    fn method(&self) -> Self::Method {
+        self.method.borrow().clone()
+    }
+
+    fn path_with_query(&self) -> Option<String> {
+        self.path_with_query.borrow().clone()
+    }
+
+    fn scheme(&self) -> Option<Self::Scheme> {
+        self.scheme.borrow().clone()
+    }
+
+    fn authority(&self) -> Option<String> {
+        self.authority.borrow().clone()
+    }
+
+    fn headers(&self) -> Self::Headers {
+        self.headers.to_immutable()
+    }
+
+    fn set_method(&self, method: &Self::Method) -> Result<(), ()> {
+        *self.method.borrow_mut() = method.clone();
+        Ok(())
+    }
+
+    fn set_path_with_query(&self, path_with_query: Option<&str>) -> Result<(), ()> {
+        *self.path_with_query.borrow_mut() = path_with_query.map(Into::into);
+        Ok(())
+    }
+
+    fn set_scheme(&self, scheme: Option<&Self::Scheme>) -> Result<(), ()> {
+        *self.scheme.borrow_mut() = scheme.cloned();
+        Ok(())
+    }
+
+    fn set_authority(&self, authority: Option<&str>) -> Result<(), ()> {
+        *self.authority.borrow_mut() = authority.map(Into::into);
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct MockRequestOptions {
+    connect_timeout: Cell<Option<Duration>>,
+    first_byte_timeout: Cell<Option<Duration>>,
+    between_bytes_timeout: Cell<Option<Duration>>,
+}
+
+impl MockRequestOptions {
+    pub fn connect_timeout(&self) -> Option<Duration> {
+        self.connect_timeout.get()
+    }
+
+    pub fn first_byte_timeout(&self) -> Option<Duration> {
+        self.first_byte_timeout.get()
+    }
+
+    pub fn between_bytes_timeout(&self) -> Option<Duration> {
+        self.between_bytes_timeout.get()
+    }
+}
+
+impl WasiRequestOptions for MockRequestOptions {
+    fn new() -> Self {
+        Self::default()
+    }
+
+    fn set_connect_timeout(&self, timeout: Option<Duration>) -> Result<(), ()> {
+        self.connect_timeout.set(timeout);
+        Ok(())
+    }
+
+    fn set_first_byte_timeout(&self, timeout: Option<Duration>) -> Result<(), ()> {
+        self.first_byte_timeout.set(timeout);
+        Ok(())
+    }
+
+    fn set_between_bytes_timeout(&self, timeout: Option<Duration>) -> Result<(), ()> {
+        self.between_bytes_timeout.set(timeout);
+        Ok(())
+    }
+}
+
+type Handler = Rc<dyn Fn(MockOutgoingRequest) -> Result<MockFutureIncomingResponse, ErrorCode>>;
+
+thread_local! {
+    static HANDLER: RefCell<Option<Handler>> = const { RefCell::new(None) };
+}
+
+/// Handles outgoing requests sent on this thread, until dropped.
+pub struct MockOutgoingHandler {
+    previous: Option<Handler>,
+}
+
+impl MockOutgoingHandler {
+    #[must_use]
+    pub fn install(
+        handler: impl Fn(MockOutgoingRequest) -> Result<MockFutureIncomingResponse, ErrorCode> + 'static,
+    ) -> Self {
+        let previous = HANDLER.with(|h| h.borrow_mut().replace(Rc::new(handler)));
+        Self { previous }
+    }
+}
+
+impl Drop for MockOutgoingHandler {
+    fn drop(&mut self) {
+        HANDLER.with(|h| *h.borrow_mut() = self.previous.take());
+    }
+}
+
+impl WasiOutgoingHandler for MockOutgoingRequest {
+    type RequestOptions = MockRequestOptions;
+    type FutureIncomingResponse = MockFutureIncomingResponse;
+    type ErrorCode = ErrorCode;
+
+    fn handle(
+        self,
+        options: Option<Self::RequestOptions>,
+    ) -> Result<Self::FutureIncomingResponse, Self::ErrorCode> {
+        *self.options.borrow_mut() = options;
+        let Some(handler) = HANDLER.with(|h| h.borrow().clone()) else {
+            return Err(ErrorCode::InternalError(Some(
+                "no outgoing handler installed".into(),
+            )));
+        };
+        handler(self)
+    }
+}
+
+impl WasiHttpBindings for MockPollable {
+    type OutgoingRequest = MockOutgoingRequest;
+}
+
+pub struct MockOutgoingResponse {
+    headers: MockFields,
+    status_code: Cell<u16>,
+    body: MockOutgoingBody,
+    body_taken: Cell<bool>,
+}
+
+impl MockOutgoingResponse {
+    /// Returns a handle to the response body, for inspecting what is written.
+    pub fn outgoing_body(&self) -> MockOutgoingBody {
+        self.body.clone()
+    }
+}
+
+impl WasiOutgoingResponse for MockOutgoingResponse {
+    type Headers = MockFields;
+    type OutgoingBody = MockOutgoingBody;
+
+    fn new(headers: Self::Headers) -> Self {
+        Self {
+            headers,
+            status_code: Cell::new(200),
+            body: Default::default(),
+            body_taken: Cell::new(false),
+        }
+    }
+
+    fn status_code(&self) -> u16 {
+        self.status_code.get()
+    }
+
+    fn headers(&self) -> Self::Headers {
+        self.headers.to_immutable()
+    }
+
+    fn set_status_code(&self, status_code: u16) -> Result<(), ()> {
+        if !(100..=999).contains(&status_code) {
+            return Err(());
+        }
+        self.status_code.set(status_code);
+        Ok(())
+    }
+
+    fn body(&self) -> Result<Self::OutgoingBody, ()> {
+        if self.body_taken.replace(true) {
+            return Err(());
+        }
+        Ok(self.body.clone())
+    }
+}
+
+/// A response outparam whose response can be taken by a test after it is set.
+#[derive(Clone, Default)]
+pub struct MockResponseOutparam {
+    response: Rc<RefCell<Option<Result<MockOutgoingResponse, ErrorCode>>>>,
+}
+
+impl MockResponseOutparam {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn take_response(&self) -> Option<Result<MockOutgoingResponse, ErrorCode>> {
+        self.response.borrow_mut().take()
+    }
+}
+
+impl WasiResponseOutparam for MockResponseOutparam {
+    type OutgoingResponse = MockOutgoingResponse;
+    type ErrorCode = ErrorCode;
+
+    fn set(self, response: Result<Self::OutgoingResponse, &Self::ErrorCode>) {
+        let mut slot = self.response.borrow_mut();
+        assert!(slot.is_none(), "response-outparam set twice");
+        *slot = Some(response.map_err(Clone::clone));
+    }
+}
@@ -0,0 +1,374 @@
+use std::{cell::RefCell, collections::VecDeque, fmt, rc::Rc};
+
+use crate::wasi::{
+    traits::{
+        WasiError, WasiInputStream, WasiOutputStream, WasiPoll, WasiStreamError, WasiSubscribe,
+    },
+    ErrorCode, StreamError,
+};
+
+use super::MockPollable;
+
+/// The number of bytes a [`MockOutputStream`] permits per `check_write` once
+/// any scripted permits are used up.
+const DEFAULT_PERMIT: u64 = 4096;
+
+/// The most a guest may pass to `blocking-write-and-flush`.
+const MAX_BLOCKING_WRITE: usize = 4096;
+
+/// Blocks until `stream` is ready, stepping the mock host as needed.
+fn wait(stream: &impl WasiSubscribe<Pollable = MockPollable>) {
+    MockPollable::poll(&[&stream.subscribe()]);
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MockIoError {
+    message: String,
+    error_code: Option<ErrorCode>,
+}
+
+impl MockIoError {
+    pub fn new(message: impl Into<String>) -> Self {
+        Self {
+            message: message.into(),
+            error_code: None,
+        }
+    }
+
+    /// Creates an error with an HTTP-level cause, as hosts report for body
+    /// streams.
+    pub fn http(error_code: ErrorCode) -> Self {
+        Self {
+            message: error_code.to_string(),
+            error_code: Some(error_code),
+        }
+    }
+}
+
+impl fmt::Display for MockIoError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl WasiError for MockIoError {
+    fn to_debug_string(&self) -> String {
+        self.message.clone()
+    }
+
+    fn http_error_code(&self) -> Option<ErrorCode> {
+        self.error_code.clone()
+    }
+}
+
+#[derive(Debug)]
+pub enum MockStreamError {
+    LastOperationFailed(MockIoError),
+    Closed,
+}
+
+impl WasiStreamError for MockStreamError {
+    type IoError = MockIoError;
+
+    fn into_stream_error(self) -> StreamError<Self::IoError> {
+        match self {
+            Self::LastOperationFailed(err) => StreamError::LastOperationFailed(err),
+            Self::Closed => StreamError::Closed,
+        }
+    }
+}
+
+/// An input stream reading from an in-memory buffer.
+#[derive(Clone, Default)]
+pub struct MockInputStream {
+    state: Rc<RefCell<InputState>>,
+}
+
+#[derive(Default)]
+struct InputState {
+    buffer: VecDeque<u8>,
+    closed: bool,
+    error: Option<MockIoError>,
+    max_read: Option<usize>,
+}
+
+impl MockInputStream {
+    /// Creates an open stream with nothing buffered.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a stream which yields `data` and is then closed.
+    pub fn with_data(data: impl AsRef<[u8]>) -> Self {
+        let stream = Self::new();
+        stream.push(data);
+        stream.close();
+        stream
+    }
+
+    /// Makes `data` available to read.
+    pub fn push(&self, data: impl AsRef<[u8]>) {
+        self.state.borrow_mut().buffer.extend(data.as_ref());
+    }
+
+    /// Closes the stream once everything buffered has been read.
+    pub fn close(&self) {
+        self.state.borrow_mut().closed = true;
+    }
+
+    /// Fails the next read with `err`, after which the stream is closed.
+    pub fn fail(&self, err: MockIoError) {
+        self.state.borrow_mut().error = Some(err);
+    }
+
+    /// Limits each read to at most `max` bytes.
+    pub fn set_max_read(&self, max: usize) {
+        self.state.borrow_mut().max_read = Some(max);
+    }
+
+    /// Returns the number of bytes buffered but not yet read.
+    pub fn remaining(&self) -> usize {
+        self.state.borrow().buffer.len()
+    }
+}
+
+impl WasiSubscribe for MockInputStream {
+    type Pollable = MockPollable;
+
+    fn subscribe(&self) -> Self::Pollable {
+        let state = self.state.clone();
+        MockPollable::new(move || {
+            let state = state.borrow();
+            !state.buffer.is_empty() || state.closed || state.error.is_some()
+        })
+    }
+}
+
+impl WasiInputStream for MockInputStream {
+    type StreamError = MockStreamError;
+
+    fn read(&self, len: u64) -> Result<Vec<u8>, Self::StreamError> {
+        let mut state = self.state.borrow_mut();
+        if let Some(err) = state.error.take() {
+            state.buffer.clear();
+            state.closed = true;
+            return Err(MockStreamError::LastOperationFailed(err));
+        }
+        if state.buffer.is_empty() {
+            return if state.closed {
+                Err(MockStreamError::Closed)
+            } else {
+                Ok(vec![])
+            };
+        }
+        let len = usize::try_from(len)
+            .unwrap_or(usize::MAX)
+            .min(state.buffer.len())
+            .min(state.max_read.unwrap_or(usize::MAX));
+        Ok(state.buffer.drain(..len).collect())
+    }
+
+    fn blocking_read(&self, len: u64) -> Result<Vec<u8>, Self::StreamError> {
+        wait(self);
+        self.read(len)
+    }
+
+    fn skip(&self, len: u64) -> Result<u64, Self::StreamError> {
+        self.read(len).map(|data| data.len() as u64)
+    }
+
+    fn blocking_skip(&self, len: u64) -> Result<u64, Self::StreamError> {
+        wait(self);
+        self.skip(len)
+    }
+}
+
+/// An output stream writing to an in-memory buffer, with scriptable
+/// `check_write` permits.
+#[derive(Clone)]
+pub struct MockOutputStream {
+    state: Rc<RefCell<OutputState>>,
+}
+
+struct OutputState {
+    written: Vec<u8>,
+    // Permitted by the last check_write and not yet written
+    permit: u64,
+    permits: VecDeque<u64>,
+    refill: Option<u64>,
+    error: Option<MockIoError>,
+    closed: bool,
+    flushes: usize,
+}
+
+impl MockOutputStream {
+    /// Creates a stream which permits writes of up to 4096 bytes at a time.
+    pub fn new() -> Self {
+        Self::with_permits([])
+    }
+
+    /// Creates a stream whose successive `check_write` calls permit
+    /// `permits` bytes before falling back to 4096 bytes at a time. A zero
+    /// permit makes the stream not ready until it is next polled.
+    pub fn with_permits(permits: impl IntoIterator<Item = u64>) -> Self {
+        Self {
+            state: Rc::new(RefCell::new(OutputState {
+                written: vec![],
+                permit: 0,
+                permits: permits.into_iter().collect(),
+                refill: Some(DEFAULT_PERMIT),
+                error: None,
+                closed: false,
+                flushes: 0,
+            })),
+        }
+    }
+
+    /// Sets the permit used once scripted permits are used up. With None,
+    /// the stream is not ready again until it is granted more with
+    /// [`MockOutputStream::grant`].
+    pub fn set_refill(&self, refill: Option<u64>) {
+        self.state.borrow_mut().refill = refill;
+    }
+
+    /// Permits another `len` bytes to be written.
+    pub fn grant(&self, len: u64) {
+        self.state.borrow_mut().permits.push_back(len);
+    }
+
+    /// Fails the next operation with `err`, after which the stream is closed.
+    pub fn fail(&self, err: MockIoError) {
+        self.state.borrow_mut().error = Some(err);
+    }
+
+    /// Closes the stream, as if the reader went away.
+    pub fn close(&self) {
+        self.state.borrow_mut().closed = true;
+    }
+
+    /// Returns everything written to the stream so far.
+    pub fn written(&self) -> Vec<u8> {
+        self.state.borrow().written.clone()
+    }
+
+    pub fn flushes(&self) -> usize {
+        self.state.borrow().flushes
+    }
+}
+
+impl Default for MockOutputStream {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl OutputState {
+    fn check_open(&mut self) -> Result<(), MockStreamError> {
+        if let Some(err) = self.error.take() {
+            self.closed = true;
+            return Err(MockStreamError::LastOperationFailed(err));
+        }
+        if self.closed {
+            return Err(MockStreamError::Closed);
+        }
+        Ok(())
+    }
+}
+
+impl WasiSubscribe for MockOutputStream {
+    type Pollable = MockPollable;
+
+    fn subscribe(&self) -> Self::Pollable {
+        let state = self.state.clone();
+        MockPollable::new(move || {
+            let state = state.borrow();
+            state.permit > 0
+                || !state.permits.is_empty()
+                || state.refill.is_some()
+                || state.error.is_some()
+                || state.closed
+        })
+    }
+}
+
+impl WasiOutputStream for MockOutputStream {
+    type InputStream = MockInputStream;
+    type StreamError = MockStreamError;
+
+    fn check_write(&self) -> Result<u64, Self::StreamError> {
+        let mut state = self.state.borrow_mut();
+        state.check_open()?;
+        if state.permit == 0 {
+            state.permit = match state.permits.pop_front() {
+                Some(permit) => permit,
+                None => state.refill.unwrap_or(0),
+            };
+        }
+        Ok(state.permit)
+    }
+
+    fn write(&self, contents: &[u8]) -> Result<(), Self::StreamError> {
+        let mut state = self.state.borrow_mut();
+        state.check_open()?;
+        let len = contents.len() as u64;
+        // A real host traps here
+        assert!(
+            len <= state.permit,
+            "write of {len} bytes exceeds the permitted {}",
+            state.permit
+        );
+        state.permit -= len;
+        state.written.extend_from_slice(contents);
+        Ok(())
+    }
+
+    fn splice(&self, src: &Self::InputStream, len: u64) -> Result<u64, Self::StreamError> {
+        let permit = self.check_write()?;
+        if permit == 0 {
+            return Ok(0);
+        }
+        let data = src.read(len.min(permit))?;
+        self.write(&data)?;
+        Ok(data.len() as u64)
+    }
+
+    fn flush(&self) -> Result<(), Self::StreamError> {
+        let mut state = self.state.borrow_mut();
+        state.check_open()?;
+        state.flushes += 1;
+        Ok(())
+    }
+
+    fn blocking_write_and_flush(&self, mut contents: &[u8]) -> Result<(), Self::StreamError> {
+        // A real host traps here
+        assert!(
+            contents.len() <= MAX_BLOCKING_WRITE,
+            "blocking write of {} bytes exceeds {MAX_BLOCKING_WRITE}",
+            contents.len()
+        );
+        while !contents.is_empty() {
+            wait(self);
+            let permit = usize::try_from(self.check_write()?).unwrap_or(usize::MAX);
+            let (chunk, rest) = contents.split_at(permit.min(contents.len()));
+            self.write(chunk)?;
+            contents = rest;
+        }
+        self.blocking_flush()
+    }
+
+    fn blocking_flush(&self) -> Result<(), Self::StreamError> {
+        self.flush()?;
+        wait(self);
+        Ok(())
+    }
+
+    fn blocking_splice(&self, src: &Self::InputStream, len: u64) -> Result<u64, Self::StreamError> {
+        wait(self);
+        wait(src);
+        self.splice(src, len)
+    }
+
+    fn write_zeroes(&self, len: u64) -> Result<(), Self::StreamError> {
+        self.write(&vec![0; len as usize])
+    }
+}
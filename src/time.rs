@@ -0,0 +1,319 @@
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use crate::{
+    poll::{PollableOrigin, PollableRegistry},
+    wasi::traits::WasiMonotonicClock,
+};
+
+/// An instant in time, in nanoseconds, as reported by
+/// `wasi:clocks/monotonic-clock.now`. Instants are only comparable to other
+/// instants from the same clock.
+pub type Instant = u64;
+
+/// Returns the current value of the monotonic clock.
+pub fn now<Registry>() -> Instant
+where
+    Registry: PollableRegistry,
+    Registry::Pollable: WasiMonotonicClock,
+{
+    Registry::Pollable::now()
+}
+
+/// Returns a future which completes once the given duration has elapsed.
+pub fn sleep<Registry>(registry: Registry, duration: Duration) -> Sleep<Registry>
+where
+    Registry: PollableRegistry,
+    Registry::Pollable: WasiMonotonicClock,
+{
+    let deadline = now::<Registry>().saturating_add(duration_nanos(duration));
+    sleep_until(registry, deadline)
+}
+
+/// Returns a future which completes once the monotonic clock reaches the
+/// given instant.
+pub fn sleep_until<Registry>(registry: Registry, deadline: Instant) -> Sleep<Registry>
+where
+    Registry: PollableRegistry,
+    Registry::Pollable: WasiMonotonicClock,
+{
+    Sleep {
+        handle: None,
+        deadline,
+        registry,
+    }
+}
+
+/// Returns an [`Interval`] which ticks once every `period`. The first tick
+/// completes immediately.
+///
+/// # Panics
+///
+/// Panics if `period` is zero.
+pub fn interval<Registry>(registry: Registry, period: Duration) -> Interval<Registry>
+where
+    Registry: PollableRegistry,
+    Registry::Pollable: WasiMonotonicClock,
+{
+    let period = duration_nanos(period);
+    assert!(period > 0, "interval period must be non-zero");
+    let start = now::<Registry>();
+    Interval {
+        sleep: sleep_until(registry, start),
+        period,
+    }
+}
+
+pub struct Sleep<Registry: PollableRegistry> {
+    handle: Option<Registry::RegisteredPollable>,
+    deadline: Instant,
+    registry: Registry,
+}
+
+impl<Registry> Sleep<Registry>
+where
+    Registry: PollableRegistry,
+    Registry::Pollable: WasiMonotonicClock,
+{
+    pub fn deadline(&self) -> Instant {
+        self.deadline
+    }
+
+    /// Resets this future to complete at the given instant instead.
+    pub fn reset(&mut self, deadline: Instant) {
+        self.handle = None;
+        self.deadline = deadline;
+    }
+
+    pub fn is_elapsed(&self) -> bool {
+        now::<Registry>() >= self.deadline
+    }
+}
+
+impl<Registry> Future for Sleep<Registry>
+where
+    Registry: PollableRegistry,
+    Registry::Pollable: WasiMonotonicClock,
+{
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        if self.is_elapsed() {
+            self.handle = None;
+            return Poll::Ready(());
+        }
+        if let Some(handle) = &self.handle {
+            if self.registry.register_waker(cx, handle) {
+                return Poll::Pending;
+            }
+        }
+        let pollable = Registry::Pollable::subscribe_instant(self.deadline);
+        self.handle = Some(self.registry.register_pollable_with_origin(
+            cx,
+            pollable,
+            PollableOrigin::Timer,
+        ));
+        Poll::Pending
+    }
+}
+
+pub struct Interval<Registry: PollableRegistry> {
+    sleep: Sleep<Registry>,
+    period: u64,
+}
+
+impl<Registry> Interval<Registry>
+where
+    Registry: PollableRegistry,
+    Registry::Pollable: WasiMonotonicClock,
+{
+    /// Polls for the next tick, returning the instant it was scheduled for.
+    /// Ticks missed because the interval was not polled in time are skipped.
+    pub fn poll_tick(&mut self, cx: &mut Context) -> Poll<Instant> {
+        if Pin::new(&mut self.sleep).poll(cx).is_pending() {
+            return Poll::Pending;
+        }
+        let tick = self.sleep.deadline();
+        let mut next = tick.saturating_add(self.period);
+        let now = now::<Registry>();
+        if next <= now {
+            next = now.saturating_add(self.period);
+        }
+        self.sleep.reset(next);
+        Poll::Ready(tick)
+    }
+
+    /// Completes at the next tick.
+    pub fn tick(&mut self) -> Tick<'_, Registry> {
+        Tick(self)
+    }
+
+    pub fn period(&self) -> Duration {
+        Duration::from_nanos(self.period)
+    }
+}
+
+#[cfg(feature = "stream")]
+impl<Registry> futures_core::Stream for Interval<Registry>
+where
+    Registry: PollableRegistry,
+    Registry::Pollable: WasiMonotonicClock,
+{
+    type Item = Instant;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.get_mut().poll_tick(cx).map(Some)
+    }
+}
+
+pub struct Tick<'a, Registry: PollableRegistry>(&'a mut Interval<Registry>);
+
+impl<'a, Registry> Future for Tick<'a, Registry>
+where
+    Registry: PollableRegistry,
+    Registry::Pollable: WasiMonotonicClock,
+{
+    type Output = Instant;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        self.0.poll_tick(cx)
+    }
+}
+
+/// Requires the given future to complete within `duration`, returning
+/// `Err(Elapsed)` otherwise.
+pub fn timeout<Registry, F>(registry: Registry, duration: Duration, fut: F) -> Timeout<Registry, F>
+where
+    Registry: PollableRegistry,
+    Registry::Pollable: WasiMonotonicClock,
+    F: Future,
+{
+    Timeout {
+        fut,
+        sleep: sleep(registry, duration),
+    }
+}
+
+pin_project_lite::pin_project! {
+    pub struct Timeout<Registry: PollableRegistry, F> {
+        #[pin]
+        fut: F,
+        sleep: Sleep<Registry>,
+    }
+}
+
+impl<Registry, F> Future for Timeout<Registry, F>
+where
+    Registry: PollableRegistry,
+    Registry::Pollable: WasiMonotonicClock,
+    F: Future,
+{
+    type Output = Result<F::Output, Elapsed>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        if let Poll::Ready(val) = this.fut.poll(cx) {
+            return Poll::Ready(Ok(val));
+        }
+        Pin::new(this.sleep).poll(cx).map(|()| Err(Elapsed))
+    }
+}
+
+#[derive(Debug)]
+pub struct Elapsed;
+
+impl std::fmt::Display for Elapsed {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "deadline has elapsed")
+    }
+}
+
+impl std::error::Error for Elapsed {}
+
+pub(crate) fn duration_nanos(duration: Duration) -> u64 {
+    duration.as_nanos().try_into().unwrap_or(u64::MAX)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::Cell, rc::Rc, time::Duration};
+
+    use crate::{
+        poll::{PollableRegistry, Poller},
+        testing::{self, MockPollable},
+    };
+
+    use super::{sleep, timeout, Elapsed};
+
+    #[test]
+    fn timeout_yields_output_of_future_that_finishes_first() {
+        let registry = Poller::<MockPollable>::default();
+        let start = testing::now();
+        let fut = async {
+            sleep(registry.clone(), Duration::from_millis(1)).await;
+            "done"
+        };
+        let res = registry
+            .block_on(timeout(registry.clone(), Duration::from_millis(10), fut))
+            .unwrap();
+        assert_eq!(res.unwrap(), "done");
+        assert_eq!(testing::now() - start, 1_000_000);
+    }
+
+    #[test]
+    fn registry_timeout_elapses() {
+        let registry = Poller::<MockPollable>::default();
+        let start = testing::now();
+        let fut = async {
+            sleep(registry.clone(), Duration::from_secs(1)).await;
+            "done"
+        };
+        let res = registry
+            .block_on(registry.timeout(Duration::from_millis(10), fut))
+            .unwrap();
+        assert!(matches!(res, Err(Elapsed)));
+        assert_eq!(testing::now() - start, 10_000_000);
+    }
+
+    #[test]
+    fn sleep_advances_mock_clock() {
+        let registry = Poller::<MockPollable>::default();
+        let start = testing::now();
+        registry
+            .block_on(sleep(registry.clone(), Duration::from_secs(5)))
+            .unwrap();
+        assert_eq!(testing::now() - start, 5_000_000_000);
+    }
+
+    #[test]
+    fn events_run_in_order_before_later_timers() {
+        let registry = Poller::<MockPollable>::default();
+        let order = Rc::new(Cell::new(0));
+        for (delay, expected) in [(2, 1), (1, 0)] {
+            let order = order.clone();
+            testing::schedule(Duration::from_millis(delay), move || {
+                assert_eq!(order.replace(expected + 1), expected);
+            });
+        }
+        registry
+            .block_on(sleep(registry.clone(), Duration::from_millis(3)))
+            .unwrap();
+        assert_eq!(order.get(), 2);
+    }
+
+    #[cfg(feature = "stream")]
+    #[test]
+    fn interval_stream_ticks_each_period() {
+        use futures_util::StreamExt;
+
+        let registry = Poller::<MockPollable>::default();
+        let start = testing::now();
+        let interval = super::interval(registry.clone(), Duration::from_millis(10));
+        let ticks: Vec<_> = registry.block_on(interval.take(3).collect()).unwrap();
+        assert_eq!(ticks, [start, start + 10_000_000, start + 20_000_000]);
+    }
+}
@@ -1,25 +1,51 @@
 use std::{
     future::{Future, IntoFuture},
-    task::{Context, Poll},
+    io,
+    marker::PhantomData,
+    ops::{Deref, DerefMut},
+    task::{ready, Context, Poll},
+    time::Duration,
 };
 
-use crate::{poll::PollableRegistry, Error};
+use crate::{
+    coop,
+    poll::{PollableOrigin, PollableRegistry},
+    Error,
+};
 
 use self::traits::{
-    WasiFields, WasiFutureIncomingResponse, WasiFutureTrailers, WasiIncomingBody,
+    WasiErrorCode, WasiFields, WasiFutureIncomingResponse, WasiFutureTrailers, WasiIncomingBody,
     WasiIncomingRequest, WasiIncomingResponse, WasiInputStream, WasiMethod, WasiOutgoingBody,
     WasiOutgoingHandler, WasiOutgoingRequest, WasiOutgoingResponse, WasiOutputStream, WasiPollable,
-    WasiResponseOutparam, WasiScheme, WasiSubscribe,
+    WasiRequestOptions, WasiResponseOutparam, WasiScheme, WasiSubscribe,
 };
 
+mod error_code;
+#[cfg(feature = "futures-io")]
+mod futures_io;
+#[cfg(feature = "wasi-0-2")]
+mod impl_0_2;
+#[cfg(feature = "wasi-2023-11-10")]
 mod impl_2023_11_10;
+mod impl_common;
+#[cfg(feature = "tokio-io")]
+mod tokio_io;
 pub mod traits;
 
+pub use error_code::{DnsErrorPayload, ErrorCode, FieldSizePayload, TlsAlertReceivedPayload};
+
+/// The most `blocking-write-and-flush` accepts at once.
+const MAX_BLOCKING_WRITE: usize = 4096;
+
+/// How much [`InputStream::poll_fill_buf`] reads at once.
+const FILL_BUF_SIZE: usize = 16 * 1024;
+
 struct Subscribable<T, Registry: PollableRegistry> {
     // NOTE: order matters; handle must be dropped before inner
     handle: Option<Registry::RegisteredPollable>,
     inner: T,
     registry: Registry,
+    origin: PollableOrigin,
 }
 
 impl<T, Registry> Subscribable<T, Registry>
@@ -27,17 +53,27 @@ where
     T: WasiSubscribe<Pollable = Registry::Pollable>,
     Registry: PollableRegistry,
 {
-    fn new(inner: T, registry: Registry) -> Self {
+    fn new(inner: T, registry: Registry, origin: PollableOrigin) -> Self {
         Self {
             handle: None,
             inner,
             registry,
+            origin,
         }
     }
 
     fn register_subscribe(&mut self, cx: &mut Context) {
+        if let Some(handle) = &self.handle {
+            // Another waiter may already be subscribed; wait alongside it
+            if self.registry.register_waker(cx, handle) {
+                return;
+            }
+        }
         let pollable = self.inner.subscribe();
-        self.handle = Some(self.registry.register_pollable(cx, pollable));
+        self.handle = Some(
+            self.registry
+                .register_pollable_with_origin(cx, pollable, self.origin),
+        );
     }
 
     fn maybe_subscribe(&mut self, cx: &mut Context) -> Poll<()> {
@@ -45,7 +81,8 @@ where
         if pollable.ready() {
             Poll::Ready(())
         } else {
-            self.handle = Some(self.registry.register_pollable(cx, pollable));
+            drop(pollable);
+            self.register_subscribe(cx);
             Poll::Pending
         }
     }
@@ -55,6 +92,14 @@ where
     }
 }
 
+impl<T, Registry: PollableRegistry> Drop for Subscribable<T, Registry> {
+    fn drop(&mut self) {
+        if let Some(handle) = self.handle.take() {
+            self.registry.release(handle);
+        }
+    }
+}
+
 impl<T, Registry: PollableRegistry> std::ops::Deref for Subscribable<T, Registry> {
     type Target = T;
 
@@ -65,6 +110,9 @@ impl<T, Registry: PollableRegistry> std::ops::Deref for Subscribable<T, Registry
 
 pub struct InputStream<Stream, Registry: PollableRegistry> {
     stream: Subscribable<Stream, Registry>,
+    // Filled by poll_fill_buf; reads drain it before the stream
+    buffered: Vec<u8>,
+    pos: usize,
 }
 
 impl<Stream, Registry> InputStream<Stream, Registry>
@@ -73,11 +121,21 @@ where
     Registry: PollableRegistry<Pollable = Stream::Pollable>,
 {
     pub fn new(stream: Stream, registry: Registry) -> Self {
-        let stream = Subscribable::new(stream, registry);
-        Self { stream }
+        let stream = Subscribable::new(stream, registry, PollableOrigin::InputStream);
+        Self {
+            stream,
+            buffered: vec![],
+            pos: 0,
+        }
     }
 
     pub fn poll_read(&mut self, cx: &mut Context, len: usize) -> Poll<Result<Vec<u8>, Error>> {
+        if let Some(data) = self.take_buffered(len) {
+            return Poll::Ready(Ok(data));
+        }
+        if coop::poll_proceed(cx).is_pending() {
+            return Poll::Pending;
+        }
         let data = self
             .stream
             .read(len.try_into().unwrap())
@@ -90,11 +148,121 @@ where
         }
     }
 
+    /// Waits until the host stream has data or is closed, without reading.
+    /// Data already buffered by [`InputStream::poll_fill_buf`] is not
+    /// considered.
+    pub fn poll_ready(&mut self, cx: &mut Context) -> Poll<()> {
+        self.stream.maybe_subscribe(cx)
+    }
+
+    /// Blocks until data is available, then reads up to `len` bytes.
+    pub fn blocking_read(&mut self, len: usize) -> Result<Vec<u8>, Error> {
+        if let Some(data) = self.take_buffered(len) {
+            return Ok(data);
+        }
+        self.stream
+            .blocking_read(len.try_into().unwrap())
+            .map_err(Error::wasi_stream_error)
+    }
+
+    /// Skips up to `len` bytes without reading them into guest memory.
+    pub fn skip(&mut self, len: u64) -> Result<u64, Error> {
+        if let Some(data) = self.take_buffered(len.try_into().unwrap_or(usize::MAX)) {
+            return Ok(data.len() as u64);
+        }
+        self.stream.skip(len).map_err(Error::wasi_stream_error)
+    }
+
+    pub fn blocking_skip(&mut self, len: u64) -> Result<u64, Error> {
+        if let Some(data) = self.take_buffered(len.try_into().unwrap_or(usize::MAX)) {
+            return Ok(data.len() as u64);
+        }
+        self.stream
+            .blocking_skip(len)
+            .map_err(Error::wasi_stream_error)
+    }
+
+    /// Returns buffered data, reading more if everything buffered has been
+    /// consumed. Data stays buffered until [`InputStream::consume`]d.
+    pub fn poll_fill_buf(&mut self, cx: &mut Context) -> Poll<Result<&[u8], Error>> {
+        if self.pos == self.buffered.len() {
+            self.buffered = ready!(self.poll_read(cx, FILL_BUF_SIZE))?;
+            self.pos = 0;
+        }
+        Poll::Ready(Ok(&self.buffered[self.pos..]))
+    }
+
+    pub fn consume(&mut self, amt: usize) {
+        self.pos = (self.pos + amt).min(self.buffered.len());
+    }
+
+    /// Returns data buffered by [`InputStream::poll_fill_buf`] which hasn't
+    /// been consumed yet.
+    pub fn buffered(&self) -> &[u8] {
+        &self.buffered[self.pos..]
+    }
+
+    fn take_buffered(&mut self, len: usize) -> Option<Vec<u8>> {
+        if self.pos == self.buffered.len() {
+            return None;
+        }
+        let end = self.pos.saturating_add(len).min(self.buffered.len());
+        let data = self.buffered[self.pos..end].to_vec();
+        self.pos = end;
+        Some(data)
+    }
+
+    // Helpers for the async I/O trait impls, which treat a closed stream as EOF
+
+    #[cfg(any(feature = "futures-io", feature = "tokio-io"))]
+    fn poll_read_io(&mut self, cx: &mut Context, buf: &mut [u8]) -> Poll<io::Result<usize>> {
+        if buf.is_empty() {
+            return Poll::Ready(Ok(0));
+        }
+        match ready!(self.poll_read(cx, buf.len())) {
+            Ok(data) => {
+                buf[..data.len()].copy_from_slice(&data);
+                Poll::Ready(Ok(data.len()))
+            }
+            Err(Error::WasiStreamClosed) => Poll::Ready(Ok(0)),
+            Err(err) => Poll::Ready(Err(err.into())),
+        }
+    }
+
+    #[cfg(any(feature = "futures-io", feature = "tokio-io"))]
+    fn poll_fill_buf_io(&mut self, cx: &mut Context) -> Poll<io::Result<&[u8]>> {
+        match ready!(self.poll_fill_buf(cx)) {
+            Ok(data) => Poll::Ready(Ok(data)),
+            Err(Error::WasiStreamClosed) => Poll::Ready(Ok(&[])),
+            Err(err) => Poll::Ready(Err(err.into())),
+        }
+    }
+
     fn registry(&self) -> &Registry {
         self.stream.registry()
     }
 }
 
+impl<Stream, Registry> io::Read for InputStream<Stream, Registry>
+where
+    Stream: WasiInputStream,
+    Registry: PollableRegistry<Pollable = Stream::Pollable>,
+{
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        match self.blocking_read(buf.len()) {
+            Ok(data) => {
+                buf[..data.len()].copy_from_slice(&data);
+                Ok(data.len())
+            }
+            Err(Error::WasiStreamClosed) => Ok(0),
+            Err(err) => Err(err.into()),
+        }
+    }
+}
+
 pub struct OutputStream<Stream, Registry: PollableRegistry> {
     stream: Subscribable<Stream, Registry>,
 }
@@ -105,14 +273,17 @@ where
     Registry: PollableRegistry<Pollable = Stream::Pollable>,
 {
     pub fn new(stream: Stream, registry: Registry) -> Self {
-        let stream = Subscribable::new(stream, registry);
+        let stream = Subscribable::new(stream, registry, PollableOrigin::OutputStream);
         Self { stream }
     }
 
     pub fn poll_check_write(
         &mut self,
         cx: &mut Context,
-    ) -> Poll<Result<OutputStreamPermit<Stream>, Error>> {
+    ) -> Poll<Result<OutputStreamPermit<'_, Stream>, Error>> {
+        if coop::poll_proceed(cx).is_pending() {
+            return Poll::Pending;
+        }
         let size = self
             .stream
             .check_write()
@@ -128,15 +299,28 @@ where
         }
     }
 
+    /// Splices up to `len` bytes from `src`. Any data `src` has buffered in
+    /// guest memory is written first, so it isn't skipped.
     pub fn poll_splice(
         &mut self,
         cx: &mut Context,
-        src: &InputStream<Stream::InputStream, Registry>,
+        src: &mut InputStream<Stream::InputStream, Registry>,
         len: u64,
     ) -> Poll<Result<u64, Error>> {
         if len == 0 {
             return Poll::Ready(Ok(0));
         }
+        if src.pos < src.buffered.len() {
+            let permit = ready!(self.poll_check_write(cx))?;
+            let buffered = &src.buffered[src.pos..];
+            let len = buffered.len().min(len.try_into().unwrap_or(usize::MAX));
+            let written = permit.write(&buffered[..len])?;
+            src.pos += written;
+            return Poll::Ready(Ok(written as u64));
+        }
+        if coop::poll_proceed(cx).is_pending() {
+            return Poll::Pending;
+        }
         let size = self
             .stream
             .splice(&src.stream.inner, len)
@@ -154,11 +338,79 @@ where
         self.stream.maybe_subscribe(cx).map(|()| Ok(()))
     }
 
+    /// Blocks until all of `contents` is written and flushed. Hosts trap if
+    /// `contents` is longer than 4096 bytes.
+    pub fn blocking_write_and_flush(&mut self, contents: &[u8]) -> Result<(), Error> {
+        self.stream
+            .blocking_write_and_flush(contents)
+            .map_err(Error::wasi_stream_error)
+    }
+
+    pub fn blocking_flush(&mut self) -> Result<(), Error> {
+        self.stream
+            .blocking_flush()
+            .map_err(Error::wasi_stream_error)
+    }
+
+    /// Like [`OutputStream::poll_splice`], blocking until the splice or write
+    /// can be made.
+    pub fn blocking_splice(
+        &mut self,
+        src: &mut InputStream<Stream::InputStream, Registry>,
+        len: u64,
+    ) -> Result<u64, Error> {
+        if src.pos < src.buffered.len() {
+            let buffered = &src.buffered[src.pos..];
+            let len = buffered
+                .len()
+                .min(MAX_BLOCKING_WRITE)
+                .min(len.try_into().unwrap_or(usize::MAX));
+            self.blocking_write_and_flush(&buffered[..len])?;
+            src.pos += len;
+            return Ok(len as u64);
+        }
+        self.stream
+            .blocking_splice(&src.stream.inner, len)
+            .map_err(Error::wasi_stream_error)
+    }
+
+    /// Writes `len` zero bytes, which must be permitted like any other write.
+    pub fn write_zeroes(&mut self, len: u64) -> Result<(), Error> {
+        self.stream
+            .write_zeroes(len)
+            .map_err(Error::wasi_stream_error)
+    }
+
+    #[cfg(any(feature = "futures-io", feature = "tokio-io"))]
+    fn poll_write_io(&mut self, cx: &mut Context, buf: &[u8]) -> Poll<io::Result<usize>> {
+        if buf.is_empty() {
+            return Poll::Ready(Ok(0));
+        }
+        let permit = ready!(self.poll_check_write(cx))?;
+        Poll::Ready(Ok(permit.write(buf)?))
+    }
+
     fn registry(&self) -> &Registry {
         self.stream.registry()
     }
 }
 
+impl<Stream, Registry> io::Write for OutputStream<Stream, Registry>
+where
+    Stream: WasiOutputStream,
+    Registry: PollableRegistry<Pollable = Stream::Pollable>,
+{
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let len = buf.len().min(MAX_BLOCKING_WRITE);
+        self.blocking_write_and_flush(&buf[..len])?;
+        Ok(len)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(self.blocking_flush()?)
+    }
+}
+
 pub struct OutputStreamPermit<'a, Stream> {
     stream: &'a Stream,
     size: u64,
@@ -210,7 +462,7 @@ where
         let Self { stream, body } = self;
         let registry = stream.registry().clone();
         drop(stream);
-        let trailers = Subscribable::new(body.finish(), registry);
+        let trailers = Subscribable::new(body.finish(), registry, PollableOrigin::FutureTrailers);
         FutureTrailers { trailers }
     }
 }
@@ -228,9 +480,12 @@ where
 
     fn poll(mut self: std::pin::Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
         match self.trailers.get() {
-            Some(Ok(Some(fields))) => Poll::Ready(Ok(Some(fields.into()))),
-            Some(Ok(None)) => Poll::Ready(Ok(None)),
-            Some(Err(err)) => Poll::Ready(Err(Error::wasi_error_code(err))),
+            Some(Ok(Ok(Some(fields)))) => Poll::Ready(Ok(Some(fields.into()))),
+            Some(Ok(Ok(None))) => Poll::Ready(Ok(None)),
+            Some(Ok(Err(err))) => Poll::Ready(Err(Error::wasi_error_code(err))),
+            Some(Err(())) => Poll::Ready(Err(Error::WasiInvalidState(
+                "FutureTrailers polled after completion",
+            ))),
             None => {
                 self.trailers.register_subscribe(cx);
                 Poll::Pending
@@ -239,6 +494,7 @@ where
     }
 }
 
+#[cfg(feature = "hyperium0")]
 pub(crate) type IncomingRequestPollable<Request> =
     <<Request as WasiIncomingRequest>::IncomingBody as WasiIncomingBody>::Pollable;
 
@@ -281,6 +537,11 @@ where
         self.request.headers().into()
     }
 
+    /// Returns the (immutable) headers without copying them.
+    pub fn fields(&self) -> FieldsRef<'_, Request::Headers> {
+        FieldsRef::new(self.request.headers())
+    }
+
     pub fn body(&mut self) -> &mut IncomingBody<Request::IncomingBody, Registry> {
         &mut self.body
     }
@@ -317,6 +578,11 @@ where
         self.response.headers().into()
     }
 
+    /// Returns the (immutable) headers without copying them.
+    pub fn fields(&self) -> FieldsRef<'_, Response::Headers> {
+        FieldsRef::new(self.response.headers())
+    }
+
     pub fn body(&mut self) -> &mut IncomingBody<Response::IncomingBody, Registry> {
         &mut self.body
     }
@@ -327,9 +593,10 @@ where
 }
 
 pub struct OutgoingBody<Body: WasiOutgoingBody, Registry: PollableRegistry> {
-    // NOTE: order matters; stream must be dropped before body
-    stream: OutputStream<Body::OutputStream, Registry>,
-    body: Body,
+    // NOTE: order matters; stream must be dropped before body. Both are None
+    // once the body has been finished in place.
+    stream: Option<OutputStream<Body::OutputStream, Registry>>,
+    body: Option<Body>,
 }
 
 impl<Body, Registry> OutgoingBody<Body, Registry>
@@ -344,24 +611,53 @@ where
                 .map_err(|()| Error::WasiInvalidState("outgoing-body.write already called"))?,
             registry,
         );
-        Ok(Self { stream, body })
+        Ok(Self {
+            stream: Some(stream),
+            body: Some(body),
+        })
     }
 
+    /// # Panics
+    ///
+    /// Panics if the body has been closed through an async I/O trait.
     pub fn stream(&mut self) -> &mut OutputStream<Body::OutputStream, Registry> {
-        &mut self.stream
+        self.stream
+            .as_mut()
+            .expect("outgoing body already finished")
+    }
+
+    pub fn finish(mut self, trailers: Option<FieldEntries>) -> Result<(), Error> {
+        self.finish_in_place(trailers)
     }
 
-    pub fn finish(self, trailers: Option<FieldEntries>) -> Result<(), Error> {
+    fn finish_in_place(&mut self, trailers: Option<FieldEntries>) -> Result<(), Error> {
         let trailers: Option<Body::Trailers> = match trailers {
             Some(trailers) => Some(trailers.try_into_fields()?),
             None => None,
         };
-        drop(self.stream);
-        self.body.finish(trailers).map_err(Error::wasi_error_code)
+        let body = self
+            .body
+            .take()
+            .ok_or(Error::WasiInvalidState("outgoing-body already finished"))?;
+        self.stream = None;
+        body.finish(trailers).map_err(Error::wasi_error_code)
+    }
+
+    /// Flushes the stream and finishes the body without trailers.
+    #[cfg(any(feature = "futures-io", feature = "tokio-io"))]
+    fn poll_close_io(&mut self, cx: &mut Context) -> Poll<io::Result<()>> {
+        let Some(stream) = &mut self.stream else {
+            return Poll::Ready(Ok(()));
+        };
+        ready!(stream.poll_flush(cx))?;
+        Poll::Ready(Ok(self.finish_in_place(None)?))
     }
 
     fn registry(&self) -> &Registry {
-        self.stream.registry()
+        self.stream
+            .as_ref()
+            .expect("outgoing body already finished")
+            .registry()
     }
 }
 
@@ -391,6 +687,39 @@ where
         Self::new(response, registry)
     }
 
+    pub fn from_fields(
+        headers: Fields<Request::Headers>,
+        registry: Registry,
+    ) -> Result<Self, Error> {
+        let request = Request::new(headers.into_wasi());
+        Self::new(request, registry)
+    }
+
+    pub fn method(&self) -> Method {
+        self.request.method().into_method()
+    }
+
+    pub fn path_with_query(&self) -> Option<String> {
+        self.request.path_with_query()
+    }
+
+    pub fn scheme(&self) -> Option<Scheme> {
+        self.request.scheme().map(|scheme| scheme.into_scheme())
+    }
+
+    pub fn authority(&self) -> Option<String> {
+        self.request.authority()
+    }
+
+    pub fn headers(&self) -> FieldEntries {
+        self.request.headers().into()
+    }
+
+    /// Returns the (immutable) headers without copying them.
+    pub fn fields(&self) -> FieldsRef<'_, Request::Headers> {
+        FieldsRef::new(self.request.headers())
+    }
+
     pub fn set_method(&mut self, method: Method) -> Result<(), Error> {
         self.request
             .set_method(&Request::Method::from_method(method))
@@ -427,14 +756,19 @@ where
 {
     pub fn send(
         self,
-        options: Option<Request::RequestOptions>,
+        options: Option<&RequestOptions>,
     ) -> Result<
         ActiveOutgoingRequest<Request::OutgoingBody, Request::FutureIncomingResponse, Registry>,
         Error,
     > {
         let Self { request, body } = self;
+        let options = options.map(RequestOptions::try_into_wasi).transpose()?;
         let response = request.handle(options).map_err(Error::wasi_error_code)?;
-        let inner = Subscribable::new(response, body.registry().clone());
+        let inner = Subscribable::new(
+            response,
+            body.registry().clone(),
+            PollableOrigin::FutureIncomingResponse,
+        );
         let future_response = FutureIncomingResponse { inner };
         Ok(ActiveOutgoingRequest {
             body,
@@ -549,6 +883,27 @@ where
         Self::new(response, registry)
     }
 
+    pub fn from_fields(
+        headers: Fields<Response::Headers>,
+        registry: Registry,
+    ) -> Result<Self, Error> {
+        let response = Response::new(headers.into_wasi());
+        Self::new(response, registry)
+    }
+
+    pub fn status_code(&self) -> u16 {
+        self.response.status_code()
+    }
+
+    pub fn headers(&self) -> FieldEntries {
+        self.response.headers().into()
+    }
+
+    /// Returns the (immutable) headers without copying them.
+    pub fn fields(&self) -> FieldsRef<'_, Response::Headers> {
+        FieldsRef::new(self.response.headers())
+    }
+
     pub fn set_status_code(&mut self, status_code: u16) -> Result<(), Error> {
         self.response
             .set_status_code(status_code)
@@ -594,12 +949,13 @@ where
         body
     }
 
-    pub fn set_error(self, err: &Outparam::ErrorCode) {
-        self.outparam.set(Err(err));
+    pub fn set_error(self, err: ErrorCode) {
+        let err = Outparam::ErrorCode::from_error_code(err);
+        self.outparam.set(Err(&err));
     }
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub enum Method {
     Get,
     Head,
@@ -613,19 +969,241 @@ pub enum Method {
     Other(String),
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub enum Scheme {
     Http,
     Https,
     Other(String),
 }
 
+/// Transport timeouts for an outgoing request. Unset timeouts are left to
+/// the host.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct RequestOptions {
+    pub connect_timeout: Option<Duration>,
+    pub first_byte_timeout: Option<Duration>,
+    pub between_bytes_timeout: Option<Duration>,
+}
+
+impl RequestOptions {
+    pub fn try_into_wasi<Options: WasiRequestOptions>(&self) -> Result<Options, Error> {
+        let options = Options::new();
+        if self.connect_timeout.is_some() {
+            options
+                .set_connect_timeout(self.connect_timeout)
+                .map_err(|()| Error::WasiInvalidValue("unsupported connect timeout"))?;
+        }
+        if self.first_byte_timeout.is_some() {
+            options
+                .set_first_byte_timeout(self.first_byte_timeout)
+                .map_err(|()| Error::WasiInvalidValue("unsupported first byte timeout"))?;
+        }
+        if self.between_bytes_timeout.is_some() {
+            options
+                .set_between_bytes_timeout(self.between_bytes_timeout)
+                .map_err(|()| Error::WasiInvalidValue("unsupported between bytes timeout"))?;
+        }
+        Ok(options)
+    }
+}
+
+/// Headers which hosts refuse to accept from guests.
+pub const FORBIDDEN_HEADERS: &[&str] = &[
+    "connection",
+    "keep-alive",
+    "proxy-authenticate",
+    "proxy-authorization",
+    "proxy-connection",
+    "te",
+    "transfer-encoding",
+    "upgrade",
+    "host",
+    "http2-settings",
+];
+
+pub fn is_forbidden_header(name: &str) -> bool {
+    FORBIDDEN_HEADERS
+        .iter()
+        .any(|forbidden| forbidden.eq_ignore_ascii_case(name))
+}
+
+/// What to do with [`FORBIDDEN_HEADERS`] found in an outgoing message.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ForbiddenHeaderPolicy {
+    /// Drop them.
+    Strip,
+    /// Fail with a [`FieldsErrorKind::Forbidden`] error, as the host would.
+    #[default]
+    Error,
+    /// Drop them, but use `Host` as the authority of a request which has
+    /// none.
+    MapHostToAuthority,
+}
+
+/// Why the host refused a header.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, thiserror::Error)]
+pub enum FieldsErrorKind {
+    #[error("invalid header syntax")]
+    InvalidSyntax,
+    #[error("forbidden header")]
+    Forbidden,
+    #[error("immutable headers")]
+    Immutable,
+}
+
+/// A header the host refused, and why.
+#[derive(Clone, Debug, PartialEq, Eq, thiserror::Error)]
+#[error("{kind}: {name:?}")]
+pub struct FieldsError {
+    pub name: String,
+    pub kind: FieldsErrorKind,
+}
+
+/// Headers or trailers held by the host, with a `HeaderMap`-like API.
+///
+/// Lookups only copy the values asked for. Fields handed out by the host for
+/// incoming messages are immutable and fail to be modified; [`Fields::clone`]
+/// returns a mutable copy.
+pub struct Fields<F> {
+    fields: F,
+}
+
+impl<F: WasiFields> Fields<F> {
+    pub fn new() -> Self {
+        Self::from_wasi(F::new())
+    }
+
+    pub fn from_wasi(fields: F) -> Self {
+        Self { fields }
+    }
+
+    pub fn into_wasi(self) -> F {
+        self.fields
+    }
+
+    /// Returns the first value of `name`.
+    pub fn get(&self, name: &str) -> Option<Vec<u8>> {
+        self.fields.get(name).into_iter().next()
+    }
+
+    pub fn get_all(&self, name: &str) -> Vec<Vec<u8>> {
+        self.fields.get(name)
+    }
+
+    pub fn contains_key(&self, name: &str) -> bool {
+        self.fields.has(name)
+    }
+
+    /// Replaces any values of `name` with `value`.
+    pub fn insert(&mut self, name: &str, value: impl Into<Vec<u8>>) -> Result<(), Error> {
+        self.fields
+            .set(name, &[value.into()])
+            .map_err(|err| Error::wasi_fields_error(name, err))
+    }
+
+    pub fn append(&mut self, name: &str, value: impl AsRef<[u8]>) -> Result<(), Error> {
+        self.fields
+            .append(name, value.as_ref())
+            .map_err(|err| Error::wasi_fields_error(name, err))
+    }
+
+    pub fn remove(&mut self, name: &str) -> Result<(), Error> {
+        self.fields
+            .delete(name)
+            .map_err(|err| Error::wasi_fields_error(name, err))
+    }
+
+    pub fn entries(&self) -> FieldEntries {
+        self.fields.entries().into()
+    }
+}
+
+/// [`Fields`] which are a child resource of a request or response, and so
+/// must not outlive it.
+pub struct FieldsRef<'a, F> {
+    fields: Fields<F>,
+    _parent: PhantomData<&'a ()>,
+}
+
+impl<F: WasiFields> FieldsRef<'_, F> {
+    fn new(fields: F) -> Self {
+        Self {
+            fields: Fields::from_wasi(fields),
+            _parent: PhantomData,
+        }
+    }
+}
+
+impl<F> Deref for FieldsRef<'_, F> {
+    type Target = Fields<F>;
+
+    fn deref(&self) -> &Fields<F> {
+        &self.fields
+    }
+}
+
+impl<F> DerefMut for FieldsRef<'_, F> {
+    fn deref_mut(&mut self) -> &mut Fields<F> {
+        &mut self.fields
+    }
+}
+
+impl<F: WasiFields> Clone for Fields<F> {
+    fn clone(&self) -> Self {
+        Self::from_wasi(self.fields.clone())
+    }
+}
+
+impl<F: WasiFields> Default for Fields<F> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 #[derive(Debug)]
 pub struct FieldEntries(Vec<(String, Vec<u8>)>);
 
 impl FieldEntries {
     pub fn try_into_fields<Fields: WasiFields>(&self) -> Result<Fields, Error> {
-        Fields::from_list(&self.0).map_err(|err| Error::WasiFieldsError(err.to_string()))
+        Fields::from_list(&self.0).map_err(|err| {
+            // from-list doesn't say which entry failed, so find it by adding
+            // the entries one at a time
+            let fields = Fields::new();
+            self.0
+                .iter()
+                .find_map(|(name, value)| {
+                    let err = fields.append(name, value).err()?;
+                    Some(Error::wasi_fields_error(name, err))
+                })
+                .unwrap_or_else(|| Error::wasi_fields_error("", err))
+        })
+    }
+
+    /// Removes forbidden headers according to `policy`, returning the first
+    /// `Host` value if the policy maps it to the authority.
+    pub fn sanitize(&mut self, policy: ForbiddenHeaderPolicy) -> Result<Option<Vec<u8>>, Error> {
+        if policy == ForbiddenHeaderPolicy::Error {
+            if let Some((name, _)) = self.0.iter().find(|(name, _)| is_forbidden_header(name)) {
+                return Err(Error::WasiFieldsError(FieldsError {
+                    name: name.clone(),
+                    kind: FieldsErrorKind::Forbidden,
+                }));
+            }
+        }
+        let mut host = None;
+        self.0.retain(|(name, value)| {
+            if !is_forbidden_header(name) {
+                return true;
+            }
+            if policy == ForbiddenHeaderPolicy::MapHostToAuthority
+                && host.is_none()
+                && name.eq_ignore_ascii_case("host")
+            {
+                host = Some(value.clone());
+            }
+            false
+        });
+        Ok(host)
     }
 }
 
@@ -655,3 +1233,172 @@ pub enum StreamError<IoError> {
     LastOperationFailed(IoError),
     Closed,
 }
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Read, Write};
+
+    use super::*;
+    use crate::{
+        poll::Poller,
+        testing::{
+            self, MockFields, MockIncomingBody, MockIncomingRequest, MockInputStream,
+            MockOutgoingRequest, MockOutgoingResponse, MockOutputStream, MockPollable,
+        },
+    };
+
+    #[test]
+    fn fields_read_and_modify_in_place() {
+        let mut fields = Fields::<MockFields>::new();
+        fields.append("accept", "text/html").unwrap();
+        fields.append("Accept", "text/plain").unwrap();
+        fields.insert("content-length", "5").unwrap();
+        assert_eq!(fields.get("content-length"), Some(b"5".to_vec()));
+        assert_eq!(fields.get_all("accept").len(), 2);
+
+        fields.insert("accept", "*/*").unwrap();
+        fields.remove("content-length").unwrap();
+        assert!(!fields.contains_key("content-length"));
+        assert_eq!(
+            fields.entries().into_iter().collect::<Vec<_>>(),
+            [("accept".to_string(), b"*/*".to_vec())]
+        );
+
+        let err = fields.insert("host", "example.com").unwrap_err();
+        assert!(matches!(
+            err,
+            Error::WasiFieldsError(FieldsError { name, kind: FieldsErrorKind::Forbidden })
+                if name == "host"
+        ));
+    }
+
+    #[test]
+    fn incoming_fields_are_immutable_until_cloned() {
+        let request =
+            MockIncomingRequest::new(Method::Get, Some("/"), MockIncomingBody::with_data(""))
+                .with_headers(vec![("content-length".into(), b"0".to_vec())]);
+        let request = IncomingRequest::new(request, Poller::<MockPollable>::default()).unwrap();
+
+        let mut fields = request.fields();
+        assert_eq!(fields.get("Content-Length"), Some(b"0".to_vec()));
+        assert!(fields.insert("x-trace", "1").is_err());
+
+        let mut copy = fields.clone();
+        copy.insert("x-trace", "1").unwrap();
+        assert!(!fields.contains_key("x-trace"));
+    }
+
+    #[test]
+    fn from_headers_reports_offending_header() {
+        let headers = FieldEntries::from(vec![
+            ("accept".to_string(), b"*/*".to_vec()),
+            ("x-bad".to_string(), b"a\r\nb".to_vec()),
+        ]);
+        let err = OutgoingRequest::<MockOutgoingRequest, _>::from_headers(
+            &headers,
+            Poller::<MockPollable>::default(),
+        )
+        .err()
+        .unwrap();
+        assert_eq!(err.to_string(), "invalid header syntax: \"x-bad\"");
+        assert!(matches!(
+            err,
+            Error::WasiFieldsError(FieldsError { name, kind: FieldsErrorKind::InvalidSyntax })
+                if name == "x-bad"
+        ));
+    }
+
+    #[test]
+    fn outgoing_state_reads_back() {
+        let registry = Poller::<MockPollable>::default();
+        let headers = FieldEntries::from(vec![("accept".to_string(), b"*/*".to_vec())]);
+        let mut request =
+            OutgoingRequest::<MockOutgoingRequest, _>::from_headers(&headers, registry.clone())
+                .unwrap();
+        request.set_method(Method::Put).unwrap();
+        request.set_path_with_query(Some("/items/1")).unwrap();
+        request.set_scheme(Some(Scheme::Https)).unwrap();
+        request.set_authority(Some("example.com")).unwrap();
+        assert_eq!(request.method(), Method::Put);
+        assert_eq!(request.path_with_query().as_deref(), Some("/items/1"));
+        assert_eq!(request.scheme(), Some(Scheme::Https));
+        assert_eq!(request.authority().as_deref(), Some("example.com"));
+        assert_eq!(request.fields().get("accept"), Some(b"*/*".to_vec()));
+        assert!(request.fields().insert("x-late", "1").is_err());
+
+        let mut response =
+            OutgoingResponse::<MockOutgoingResponse, _>::from_headers(&headers, registry).unwrap();
+        response.set_status_code(404).unwrap();
+        assert_eq!(response.status_code(), 404);
+        assert_eq!(response.headers().into_iter().count(), 1);
+    }
+
+    #[test]
+    fn std_io_read_blocks_until_data_arrives() {
+        let stream = MockInputStream::new();
+        testing::schedule(Duration::from_secs(1), {
+            let stream = stream.clone();
+            move || stream.push("hello ")
+        });
+        testing::schedule(Duration::from_secs(2), {
+            let stream = stream.clone();
+            move || {
+                stream.push("world");
+                stream.close();
+            }
+        });
+
+        let mut input = InputStream::new(stream, Poller::<MockPollable>::default());
+        let mut read = String::new();
+        input.read_to_string(&mut read).unwrap();
+        assert_eq!(read, "hello world");
+        assert_eq!(testing::now(), 2_000_000_000);
+    }
+
+    #[test]
+    fn std_io_write_flushes_each_chunk() {
+        let stream = MockOutputStream::with_permits([3, 0, 2]);
+        let mut output = OutputStream::new(stream.clone(), Poller::<MockPollable>::default());
+        output.write_all(b"hello world").unwrap();
+        output.flush().unwrap();
+        assert_eq!(stream.written(), b"hello world");
+        assert_eq!(stream.flushes(), 2);
+
+        stream.close();
+        let err = output.write(b"more").unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::BrokenPipe);
+    }
+
+    #[test]
+    fn splice_writes_buffered_data_first() {
+        let registry = Poller::<MockPollable>::default();
+        let stream = MockInputStream::new();
+        stream.push("hello");
+        let mut input = InputStream::new(stream.clone(), registry.clone());
+        registry
+            .block_on(std::future::poll_fn(|cx| {
+                input.poll_fill_buf(cx).map_ok(|data| data.len())
+            }))
+            .unwrap()
+            .unwrap();
+        input.consume(2);
+
+        // Only the host stream counts towards readiness
+        let waker = crate::poll::noop_waker();
+        let mut cx = Context::from_waker(&waker);
+        assert!(input.poll_ready(&mut cx).is_pending());
+        stream.push(" world");
+        stream.close();
+        assert!(input.poll_ready(&mut cx).is_ready());
+
+        let output = MockOutputStream::new();
+        let mut out = OutputStream::new(output.clone(), registry);
+        assert_eq!(out.blocking_splice(&mut input, 64).unwrap(), 3);
+        assert_eq!(out.blocking_splice(&mut input, 64).unwrap(), 6);
+        assert!(matches!(
+            out.blocking_splice(&mut input, 64),
+            Err(Error::WasiStreamClosed)
+        ));
+        assert_eq!(output.written(), b"llo world");
+    }
+}
@@ -0,0 +1,373 @@
+/// The `wasi:http/types.error-code` variant, independent of any bindings.
+#[derive(Clone, Debug, PartialEq, Eq, thiserror::Error)]
+pub enum ErrorCode {
+    #[error("DNS timeout")]
+    DnsTimeout,
+    #[error("DNS error: {0}")]
+    DnsError(DnsErrorPayload),
+    #[error("destination not found")]
+    DestinationNotFound,
+    #[error("destination unavailable")]
+    DestinationUnavailable,
+    #[error("destination IP prohibited")]
+    DestinationIpProhibited,
+    #[error("destination IP unroutable")]
+    DestinationIpUnroutable,
+    #[error("connection refused")]
+    ConnectionRefused,
+    #[error("connection terminated")]
+    ConnectionTerminated,
+    #[error("connection timeout")]
+    ConnectionTimeout,
+    #[error("connection read timeout")]
+    ConnectionReadTimeout,
+    #[error("connection write timeout")]
+    ConnectionWriteTimeout,
+    #[error("connection limit reached")]
+    ConnectionLimitReached,
+    #[error("TLS protocol error")]
+    TlsProtocolError,
+    #[error("TLS certificate error")]
+    TlsCertificateError,
+    #[error("TLS alert received: {0}")]
+    TlsAlertReceived(TlsAlertReceivedPayload),
+    #[error("HTTP request denied")]
+    HttpRequestDenied,
+    #[error("HTTP request length required")]
+    HttpRequestLengthRequired,
+    #[error("HTTP request body size{}", OptDisplay(.0))]
+    HttpRequestBodySize(Option<u64>),
+    #[error("HTTP request method invalid")]
+    HttpRequestMethodInvalid,
+    #[error("HTTP request URI invalid")]
+    HttpRequestUriInvalid,
+    #[error("HTTP request URI too long")]
+    HttpRequestUriTooLong,
+    #[error("HTTP request header section size{}", OptDisplay(.0))]
+    HttpRequestHeaderSectionSize(Option<u32>),
+    #[error("HTTP request header size{}", OptDisplay(.0))]
+    HttpRequestHeaderSize(Option<FieldSizePayload>),
+    #[error("HTTP request trailer section size{}", OptDisplay(.0))]
+    HttpRequestTrailerSectionSize(Option<u32>),
+    #[error("HTTP request trailer size: {0}")]
+    HttpRequestTrailerSize(FieldSizePayload),
+    #[error("HTTP response incomplete")]
+    HttpResponseIncomplete,
+    #[error("HTTP response header section size{}", OptDisplay(.0))]
+    HttpResponseHeaderSectionSize(Option<u32>),
+    #[error("HTTP response header size: {0}")]
+    HttpResponseHeaderSize(FieldSizePayload),
+    #[error("HTTP response body size{}", OptDisplay(.0))]
+    HttpResponseBodySize(Option<u64>),
+    #[error("HTTP response trailer section size{}", OptDisplay(.0))]
+    HttpResponseTrailerSectionSize(Option<u32>),
+    #[error("HTTP response trailer size: {0}")]
+    HttpResponseTrailerSize(FieldSizePayload),
+    #[error("HTTP response transfer coding{}", OptDisplay(.0))]
+    HttpResponseTransferCoding(Option<String>),
+    #[error("HTTP response content coding{}", OptDisplay(.0))]
+    HttpResponseContentCoding(Option<String>),
+    #[error("HTTP response timeout")]
+    HttpResponseTimeout,
+    #[error("HTTP upgrade failed")]
+    HttpUpgradeFailed,
+    #[error("HTTP protocol error")]
+    HttpProtocolError,
+    #[error("loop detected")]
+    LoopDetected,
+    #[error("configuration error")]
+    ConfigurationError,
+    /// A catch-all for errors not captured by the other variants.
+    #[error("internal error{}", OptDisplay(.0))]
+    InternalError(Option<String>),
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct DnsErrorPayload {
+    pub rcode: Option<String>,
+    pub info_code: Option<u16>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TlsAlertReceivedPayload {
+    pub alert_id: Option<u8>,
+    pub alert_message: Option<String>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct FieldSizePayload {
+    pub field_name: Option<String>,
+    pub field_size: Option<u32>,
+}
+
+impl std::fmt::Display for DnsErrorPayload {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write_fields(
+            f,
+            &[
+                ("rcode", self.rcode.as_ref().map(|v| v as _)),
+                ("info-code", self.info_code.as_ref().map(|v| v as _)),
+            ],
+        )
+    }
+}
+
+impl std::fmt::Display for TlsAlertReceivedPayload {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write_fields(
+            f,
+            &[
+                ("alert-id", self.alert_id.as_ref().map(|v| v as _)),
+                ("alert-message", self.alert_message.as_ref().map(|v| v as _)),
+            ],
+        )
+    }
+}
+
+impl std::fmt::Display for FieldSizePayload {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write_fields(
+            f,
+            &[
+                ("field-name", self.field_name.as_ref().map(|v| v as _)),
+                ("field-size", self.field_size.as_ref().map(|v| v as _)),
+            ],
+        )
+    }
+}
+
+/// Writes the fields which are present as `name=value`, or "unknown" if
+/// there are none.
+fn write_fields(
+    f: &mut std::fmt::Formatter<'_>,
+    fields: &[(&str, Option<&dyn std::fmt::Display>)],
+) -> std::fmt::Result {
+    let mut present = fields
+        .iter()
+        .filter_map(|(name, value)| Some((name, value.as_ref()?)))
+        .peekable();
+    if present.peek().is_none() {
+        return f.write_str("unknown");
+    }
+    for (idx, (name, value)) in present.enumerate() {
+        if idx > 0 {
+            f.write_str(" ")?;
+        }
+        write!(f, "{name}={value}")?;
+    }
+    Ok(())
+}
+
+/// Displays nothing for None, and ": {value}" otherwise.
+struct OptDisplay<'a, T>(&'a Option<T>);
+
+impl<T: std::fmt::Display> std::fmt::Display for OptDisplay<'_, T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.0 {
+            Some(value) => write!(f, ": {value}"),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Implements [`WasiErrorCode`](super::traits::WasiErrorCode) for the
+/// `wasi::http::types::ErrorCode` in scope, which is the same in every
+/// supported version.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __impl_wasi_error_code {
+    () => {
+        impl $crate::wasi::traits::WasiErrorCode for wasi::http::types::ErrorCode {
+            fn to_error_code(&self) -> $crate::wasi::ErrorCode {
+                use wasi::http::types::ErrorCode as Wasi;
+                use $crate::wasi::ErrorCode as Crate;
+                match self {
+                    Wasi::DnsTimeout => Crate::DnsTimeout,
+                    Wasi::DnsError(p) => Crate::DnsError($crate::wasi::DnsErrorPayload {
+                        rcode: p.rcode.clone(),
+                        info_code: p.info_code,
+                    }),
+                    Wasi::DestinationNotFound => Crate::DestinationNotFound,
+                    Wasi::DestinationUnavailable => Crate::DestinationUnavailable,
+                    Wasi::DestinationIpProhibited => Crate::DestinationIpProhibited,
+                    Wasi::DestinationIpUnroutable => Crate::DestinationIpUnroutable,
+                    Wasi::ConnectionRefused => Crate::ConnectionRefused,
+                    Wasi::ConnectionTerminated => Crate::ConnectionTerminated,
+                    Wasi::ConnectionTimeout => Crate::ConnectionTimeout,
+                    Wasi::ConnectionReadTimeout => Crate::ConnectionReadTimeout,
+                    Wasi::ConnectionWriteTimeout => Crate::ConnectionWriteTimeout,
+                    Wasi::ConnectionLimitReached => Crate::ConnectionLimitReached,
+                    Wasi::TlsProtocolError => Crate::TlsProtocolError,
+                    Wasi::TlsCertificateError => Crate::TlsCertificateError,
+                    Wasi::TlsAlertReceived(p) => {
+                        Crate::TlsAlertReceived($crate::wasi::TlsAlertReceivedPayload {
+                            alert_id: p.alert_id,
+                            alert_message: p.alert_message.clone(),
+                        })
+                    }
+                    Wasi::HttpRequestDenied => Crate::HttpRequestDenied,
+                    Wasi::HttpRequestLengthRequired => Crate::HttpRequestLengthRequired,
+                    Wasi::HttpRequestBodySize(s) => Crate::HttpRequestBodySize(*s),
+                    Wasi::HttpRequestMethodInvalid => Crate::HttpRequestMethodInvalid,
+                    Wasi::HttpRequestUriInvalid => Crate::HttpRequestUriInvalid,
+                    Wasi::HttpRequestUriTooLong => Crate::HttpRequestUriTooLong,
+                    Wasi::HttpRequestHeaderSectionSize(s) => {
+                        Crate::HttpRequestHeaderSectionSize(*s)
+                    }
+                    Wasi::HttpRequestHeaderSize(p) => {
+                        Crate::HttpRequestHeaderSize(p.as_ref().map(|p| {
+                            $crate::wasi::FieldSizePayload {
+                                field_name: p.field_name.clone(),
+                                field_size: p.field_size,
+                            }
+                        }))
+                    }
+                    Wasi::HttpRequestTrailerSectionSize(s) => {
+                        Crate::HttpRequestTrailerSectionSize(*s)
+                    }
+                    Wasi::HttpRequestTrailerSize(p) => {
+                        Crate::HttpRequestTrailerSize($crate::wasi::FieldSizePayload {
+                            field_name: p.field_name.clone(),
+                            field_size: p.field_size,
+                        })
+                    }
+                    Wasi::HttpResponseIncomplete => Crate::HttpResponseIncomplete,
+                    Wasi::HttpResponseHeaderSectionSize(s) => {
+                        Crate::HttpResponseHeaderSectionSize(*s)
+                    }
+                    Wasi::HttpResponseHeaderSize(p) => {
+                        Crate::HttpResponseHeaderSize($crate::wasi::FieldSizePayload {
+                            field_name: p.field_name.clone(),
+                            field_size: p.field_size,
+                        })
+                    }
+                    Wasi::HttpResponseBodySize(s) => Crate::HttpResponseBodySize(*s),
+                    Wasi::HttpResponseTrailerSectionSize(s) => {
+                        Crate::HttpResponseTrailerSectionSize(*s)
+                    }
+                    Wasi::HttpResponseTrailerSize(p) => {
+                        Crate::HttpResponseTrailerSize($crate::wasi::FieldSizePayload {
+                            field_name: p.field_name.clone(),
+                            field_size: p.field_size,
+                        })
+                    }
+                    Wasi::HttpResponseTransferCoding(s) => {
+                        Crate::HttpResponseTransferCoding(s.clone())
+                    }
+                    Wasi::HttpResponseContentCoding(s) => {
+                        Crate::HttpResponseContentCoding(s.clone())
+                    }
+                    Wasi::HttpResponseTimeout => Crate::HttpResponseTimeout,
+                    Wasi::HttpUpgradeFailed => Crate::HttpUpgradeFailed,
+                    Wasi::HttpProtocolError => Crate::HttpProtocolError,
+                    Wasi::LoopDetected => Crate::LoopDetected,
+                    Wasi::ConfigurationError => Crate::ConfigurationError,
+                    Wasi::InternalError(s) => Crate::InternalError(s.clone()),
+                }
+            }
+
+            fn from_error_code(code: $crate::wasi::ErrorCode) -> Self {
+                use wasi::http::types::ErrorCode as Wasi;
+                use $crate::wasi::ErrorCode as Crate;
+                match code {
+                    Crate::DnsTimeout => Wasi::DnsTimeout,
+                    Crate::DnsError(p) => Wasi::DnsError(wasi::http::types::DnsErrorPayload {
+                        rcode: p.rcode,
+                        info_code: p.info_code,
+                    }),
+                    Crate::DestinationNotFound => Wasi::DestinationNotFound,
+                    Crate::DestinationUnavailable => Wasi::DestinationUnavailable,
+                    Crate::DestinationIpProhibited => Wasi::DestinationIpProhibited,
+                    Crate::DestinationIpUnroutable => Wasi::DestinationIpUnroutable,
+                    Crate::ConnectionRefused => Wasi::ConnectionRefused,
+                    Crate::ConnectionTerminated => Wasi::ConnectionTerminated,
+                    Crate::ConnectionTimeout => Wasi::ConnectionTimeout,
+                    Crate::ConnectionReadTimeout => Wasi::ConnectionReadTimeout,
+                    Crate::ConnectionWriteTimeout => Wasi::ConnectionWriteTimeout,
+                    Crate::ConnectionLimitReached => Wasi::ConnectionLimitReached,
+                    Crate::TlsProtocolError => Wasi::TlsProtocolError,
+                    Crate::TlsCertificateError => Wasi::TlsCertificateError,
+                    Crate::TlsAlertReceived(p) => {
+                        Wasi::TlsAlertReceived(wasi::http::types::TlsAlertReceivedPayload {
+                            alert_id: p.alert_id,
+                            alert_message: p.alert_message,
+                        })
+                    }
+                    Crate::HttpRequestDenied => Wasi::HttpRequestDenied,
+                    Crate::HttpRequestLengthRequired => Wasi::HttpRequestLengthRequired,
+                    Crate::HttpRequestBodySize(s) => Wasi::HttpRequestBodySize(s),
+                    Crate::HttpRequestMethodInvalid => Wasi::HttpRequestMethodInvalid,
+                    Crate::HttpRequestUriInvalid => Wasi::HttpRequestUriInvalid,
+                    Crate::HttpRequestUriTooLong => Wasi::HttpRequestUriTooLong,
+                    Crate::HttpRequestHeaderSectionSize(s) => Wasi::HttpRequestHeaderSectionSize(s),
+                    Crate::HttpRequestHeaderSize(p) => Wasi::HttpRequestHeaderSize(p.map(|p| {
+                        wasi::http::types::FieldSizePayload {
+                            field_name: p.field_name,
+                            field_size: p.field_size,
+                        }
+                    })),
+                    Crate::HttpRequestTrailerSectionSize(s) => {
+                        Wasi::HttpRequestTrailerSectionSize(s)
+                    }
+                    Crate::HttpRequestTrailerSize(p) => {
+                        Wasi::HttpRequestTrailerSize(wasi::http::types::FieldSizePayload {
+                            field_name: p.field_name,
+                            field_size: p.field_size,
+                        })
+                    }
+                    Crate::HttpResponseIncomplete => Wasi::HttpResponseIncomplete,
+                    Crate::HttpResponseHeaderSectionSize(s) => {
+                        Wasi::HttpResponseHeaderSectionSize(s)
+                    }
+                    Crate::HttpResponseHeaderSize(p) => {
+                        Wasi::HttpResponseHeaderSize(wasi::http::types::FieldSizePayload {
+                            field_name: p.field_name,
+                            field_size: p.field_size,
+                        })
+                    }
+                    Crate::HttpResponseBodySize(s) => Wasi::HttpResponseBodySize(s),
+                    Crate::HttpResponseTrailerSectionSize(s) => {
+                        Wasi::HttpResponseTrailerSectionSize(s)
+                    }
+                    Crate::HttpResponseTrailerSize(p) => {
+                        Wasi::HttpResponseTrailerSize(wasi::http::types::FieldSizePayload {
+                            field_name: p.field_name,
+                            field_size: p.field_size,
+                        })
+                    }
+                    Crate::HttpResponseTransferCoding(s) => Wasi::HttpResponseTransferCoding(s),
+                    Crate::HttpResponseContentCoding(s) => Wasi::HttpResponseContentCoding(s),
+                    Crate::HttpResponseTimeout => Wasi::HttpResponseTimeout,
+                    Crate::HttpUpgradeFailed => Wasi::HttpUpgradeFailed,
+                    Crate::HttpProtocolError => Wasi::HttpProtocolError,
+                    Crate::LoopDetected => Wasi::LoopDetected,
+                    Crate::ConfigurationError => Wasi::ConfigurationError,
+                    Crate::InternalError(s) => Wasi::InternalError(s),
+                }
+            }
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DnsErrorPayload, ErrorCode, FieldSizePayload};
+
+    #[test]
+    fn display_includes_present_payload_fields() {
+        let code = ErrorCode::DnsError(DnsErrorPayload {
+            rcode: Some("NXDOMAIN".into()),
+            info_code: None,
+        });
+        assert_eq!(code.to_string(), "DNS error: rcode=NXDOMAIN");
+
+        let code = ErrorCode::HttpResponseHeaderSize(FieldSizePayload::default());
+        assert_eq!(code.to_string(), "HTTP response header size: unknown");
+
+        assert_eq!(
+            ErrorCode::HttpRequestBodySize(Some(10)).to_string(),
+            "HTTP request body size: 10"
+        );
+        assert_eq!(ErrorCode::InternalError(None).to_string(), "internal error");
+    }
+}
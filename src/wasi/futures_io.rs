@@ -0,0 +1,167 @@
+use std::{
+    io,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use futures_io::{AsyncBufRead, AsyncRead, AsyncWrite};
+
+use crate::poll::PollableRegistry;
+
+use super::{
+    traits::{WasiIncomingBody, WasiInputStream, WasiOutgoingBody, WasiOutputStream},
+    IncomingBody, InputStream, OutgoingBody, OutputStream,
+};
+
+impl<Stream, Registry> AsyncRead for InputStream<Stream, Registry>
+where
+    Stream: WasiInputStream,
+    Registry: PollableRegistry<Pollable = Stream::Pollable>,
+{
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        self.get_mut().poll_read_io(cx, buf)
+    }
+}
+
+impl<Stream, Registry> AsyncBufRead for InputStream<Stream, Registry>
+where
+    Stream: WasiInputStream,
+    Registry: PollableRegistry<Pollable = Stream::Pollable>,
+{
+    fn poll_fill_buf(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<&[u8]>> {
+        self.get_mut().poll_fill_buf_io(cx)
+    }
+
+    fn consume(self: Pin<&mut Self>, amt: usize) {
+        self.get_mut().consume(amt)
+    }
+}
+
+impl<Body, Registry> AsyncRead for IncomingBody<Body, Registry>
+where
+    Body: WasiIncomingBody<Pollable = Registry::Pollable>,
+    Registry: PollableRegistry,
+{
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        self.get_mut().stream().poll_read_io(cx, buf)
+    }
+}
+
+impl<Body, Registry> AsyncBufRead for IncomingBody<Body, Registry>
+where
+    Body: WasiIncomingBody<Pollable = Registry::Pollable>,
+    Registry: PollableRegistry,
+{
+    fn poll_fill_buf(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<&[u8]>> {
+        self.get_mut().stream().poll_fill_buf_io(cx)
+    }
+
+    fn consume(self: Pin<&mut Self>, amt: usize) {
+        self.get_mut().stream().consume(amt)
+    }
+}
+
+impl<Stream, Registry> AsyncWrite for OutputStream<Stream, Registry>
+where
+    Stream: WasiOutputStream,
+    Registry: PollableRegistry<Pollable = Stream::Pollable>,
+{
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        self.get_mut().poll_write_io(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.get_mut().poll_flush(cx).map_err(Into::into)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.poll_flush(cx)
+    }
+}
+
+/// Closing the body finishes it without trailers.
+impl<Body, Registry> AsyncWrite for OutgoingBody<Body, Registry>
+where
+    Body: WasiOutgoingBody,
+    Body::OutputStream: WasiOutputStream<Pollable = Registry::Pollable>,
+    Registry: PollableRegistry,
+{
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        self.get_mut().stream().poll_write_io(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match &mut self.get_mut().stream {
+            Some(stream) => stream.poll_flush(cx).map_err(Into::into),
+            None => Poll::Ready(Ok(())),
+        }
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.get_mut().poll_close_io(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures_util::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt};
+
+    use crate::{
+        poll::{PollableRegistry, Poller},
+        testing::{MockIncomingBody, MockOutgoingBody, MockOutputStream, MockPollable},
+        wasi::{IncomingBody, OutgoingBody},
+    };
+
+    #[test]
+    fn reads_incoming_body_lines() {
+        let registry = Poller::<MockPollable>::default();
+        let wasi_body = MockIncomingBody::with_data("one\ntwo\n");
+        let mut body = IncomingBody::new(wasi_body, registry.clone()).unwrap();
+
+        let mut line = String::new();
+        registry
+            .block_on(body.read_line(&mut line))
+            .unwrap()
+            .unwrap();
+        assert_eq!(line, "one\n");
+        let mut rest = String::new();
+        registry
+            .block_on(body.read_to_string(&mut rest))
+            .unwrap()
+            .unwrap();
+        assert_eq!(rest, "two\n");
+    }
+
+    #[test]
+    fn close_finishes_outgoing_body() {
+        let registry = Poller::<MockPollable>::default();
+        let wasi_body = MockOutgoingBody::new(MockOutputStream::with_permits([2]));
+        let mut body = OutgoingBody::new(wasi_body.clone(), registry.clone()).unwrap();
+
+        registry
+            .block_on(async {
+                body.write_all(b"hello").await?;
+                body.close().await
+            })
+            .unwrap()
+            .unwrap();
+        assert_eq!(wasi_body.written(), b"hello");
+        assert!(wasi_body.is_finished());
+    }
+}
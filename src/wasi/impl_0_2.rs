@@ -0,0 +1,106 @@
+/// Implements the traits in [`crate::wasi::traits`] for bindings generated
+/// from `wasi:http@0.2.0`.
+#[macro_export]
+macro_rules! impl_wasi_0_2_0 {
+    ($wasi_module_path:tt) => {
+        $crate::__impl_wasi_0_2!($wasi_module_path, impl_wasi_traits_0_2_0);
+    };
+}
+
+/// Implements the traits in [`crate::wasi::traits`] for bindings generated
+/// from any `wasi:http@0.2.x` release. Minor releases only add to the 0.2.0
+/// interfaces, so this is currently the same as [`impl_wasi_0_2_0!`]; a
+/// release whose additions map onto the traits gets its own
+/// `impl_wasi_0_2_N!`, which this then follows.
+#[macro_export]
+macro_rules! impl_wasi_0_2 {
+    ($wasi_module_path:tt) => {
+        $crate::__impl_wasi_0_2!($wasi_module_path, impl_wasi_traits_0_2);
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __impl_wasi_0_2 {
+    ($wasi_module_path:tt, $impl_module:ident) => {
+        mod $impl_module {
+            use super::$wasi_module_path as wasi;
+
+            $crate::__impl_wasi_common! {
+                fields: {
+                    fn has(&self, name: &str) -> bool {
+                        self.has(&name.to_string())
+                    }
+                },
+                future_trailers: {
+                    fn get(
+                        &self,
+                    ) -> Option<Result<Result<Option<Self::Trailers>, Self::ErrorCode>, ()>> {
+                        self.get()
+                    }
+                },
+                request_options: {
+                    fn set_connect_timeout(
+                        &self,
+                        timeout: Option<std::time::Duration>,
+                    ) -> Result<(), ()> {
+                        self.set_connect_timeout(
+                            timeout.map(|t| t.as_nanos().try_into().unwrap_or(u64::MAX)),
+                        )
+                    }
+
+                    fn set_first_byte_timeout(
+                        &self,
+                        timeout: Option<std::time::Duration>,
+                    ) -> Result<(), ()> {
+                        self.set_first_byte_timeout(
+                            timeout.map(|t| t.as_nanos().try_into().unwrap_or(u64::MAX)),
+                        )
+                    }
+
+                    fn set_between_bytes_timeout(
+                        &self,
+                        timeout: Option<std::time::Duration>,
+                    ) -> Result<(), ()> {
+                        self.set_between_bytes_timeout(
+                            timeout.map(|t| t.as_nanos().try_into().unwrap_or(u64::MAX)),
+                        )
+                    }
+                },
+            }
+        }
+    };
+}
+
+#[cfg(test)]
+mod type_check_macro {
+    wit_bindgen::generate!({
+        path: "wit/0.2.0",
+        world: "test",
+    });
+    impl_wasi_0_2_0!(wasi);
+
+    mod any_0_2 {
+        wit_bindgen::generate!({
+            path: "wit/0.2.0",
+            world: "test",
+        });
+        impl_wasi_0_2!(wasi);
+    }
+
+    #[allow(unused_imports)]
+    use crate::wasi::traits;
+
+    // Send-requiring middleware (e.g. tower's Buffer) must be able to wrap a client
+    #[cfg(feature = "hyperium1")]
+    #[allow(dead_code)]
+    fn client_is_send() {
+        use crate::{hyperium1::WasiClient, poll::Poller, IncomingHttpBody};
+
+        type Registry = Poller<wasi::io::poll::Pollable>;
+        type Request = http1::Request<http_body_util::Full<bytes::Bytes>>;
+        fn assert_send<T: Send>() {}
+        assert_send::<<WasiClient<Registry> as tower_service::Service<Request>>::Future>();
+        assert_send::<IncomingHttpBody<wasi::http::types::IncomingBody, Registry>>();
+    }
+}
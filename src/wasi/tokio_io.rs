@@ -0,0 +1,169 @@
+use std::{
+    io,
+    pin::Pin,
+    task::{ready, Context, Poll},
+};
+
+use tokio::io::{AsyncBufRead, AsyncRead, AsyncWrite, ReadBuf};
+
+use crate::poll::PollableRegistry;
+
+use super::{
+    traits::{WasiIncomingBody, WasiInputStream, WasiOutgoingBody, WasiOutputStream},
+    IncomingBody, InputStream, OutgoingBody, OutputStream,
+};
+
+fn poll_read_buf<Stream, Registry>(
+    stream: &mut InputStream<Stream, Registry>,
+    cx: &mut Context<'_>,
+    buf: &mut ReadBuf<'_>,
+) -> Poll<io::Result<()>>
+where
+    Stream: WasiInputStream,
+    Registry: PollableRegistry<Pollable = Stream::Pollable>,
+{
+    let len = ready!(stream.poll_read_io(cx, buf.initialize_unfilled()))?;
+    buf.advance(len);
+    Poll::Ready(Ok(()))
+}
+
+impl<Stream, Registry> AsyncRead for InputStream<Stream, Registry>
+where
+    Stream: WasiInputStream,
+    Registry: PollableRegistry<Pollable = Stream::Pollable>,
+{
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        poll_read_buf(self.get_mut(), cx, buf)
+    }
+}
+
+impl<Stream, Registry> AsyncBufRead for InputStream<Stream, Registry>
+where
+    Stream: WasiInputStream,
+    Registry: PollableRegistry<Pollable = Stream::Pollable>,
+{
+    fn poll_fill_buf(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<&[u8]>> {
+        self.get_mut().poll_fill_buf_io(cx)
+    }
+
+    fn consume(self: Pin<&mut Self>, amt: usize) {
+        self.get_mut().consume(amt)
+    }
+}
+
+impl<Body, Registry> AsyncRead for IncomingBody<Body, Registry>
+where
+    Body: WasiIncomingBody<Pollable = Registry::Pollable>,
+    Registry: PollableRegistry,
+{
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        poll_read_buf(self.get_mut().stream(), cx, buf)
+    }
+}
+
+impl<Body, Registry> AsyncBufRead for IncomingBody<Body, Registry>
+where
+    Body: WasiIncomingBody<Pollable = Registry::Pollable>,
+    Registry: PollableRegistry,
+{
+    fn poll_fill_buf(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<&[u8]>> {
+        self.get_mut().stream().poll_fill_buf_io(cx)
+    }
+
+    fn consume(self: Pin<&mut Self>, amt: usize) {
+        self.get_mut().stream().consume(amt)
+    }
+}
+
+impl<Stream, Registry> AsyncWrite for OutputStream<Stream, Registry>
+where
+    Stream: WasiOutputStream,
+    Registry: PollableRegistry<Pollable = Stream::Pollable>,
+{
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        self.get_mut().poll_write_io(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.get_mut().poll_flush(cx).map_err(Into::into)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.poll_flush(cx)
+    }
+}
+
+/// Shutting down the body finishes it without trailers.
+impl<Body, Registry> AsyncWrite for OutgoingBody<Body, Registry>
+where
+    Body: WasiOutgoingBody,
+    Body::OutputStream: WasiOutputStream<Pollable = Registry::Pollable>,
+    Registry: PollableRegistry,
+{
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        self.get_mut().stream().poll_write_io(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match &mut self.get_mut().stream {
+            Some(stream) => stream.poll_flush(cx).map_err(Into::into),
+            None => Poll::Ready(Ok(())),
+        }
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.get_mut().poll_close_io(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    use crate::{
+        poll::{PollableRegistry, Poller},
+        testing::{MockIncomingBody, MockOutgoingBody, MockOutputStream, MockPollable},
+        wasi::{IncomingBody, OutgoingBody},
+    };
+
+    #[test]
+    fn copies_incoming_to_outgoing() {
+        let registry = Poller::<MockPollable>::default();
+        let incoming = MockIncomingBody::with_data("hello world");
+        let mut src = IncomingBody::new(incoming, registry.clone()).unwrap();
+        let outgoing = MockOutgoingBody::new(MockOutputStream::with_permits([4, 0, 3]));
+        let mut dest = OutgoingBody::new(outgoing.clone(), registry.clone()).unwrap();
+
+        let copied = registry
+            .block_on(async {
+                let copied = tokio::io::copy(&mut src, &mut dest).await?;
+                dest.shutdown().await?;
+                Ok::<_, std::io::Error>(copied)
+            })
+            .unwrap()
+            .unwrap();
+        assert_eq!(copied, 11);
+        assert_eq!(outgoing.written(), b"hello world");
+        assert!(outgoing.is_finished());
+
+        let mut rest = vec![];
+        let read = registry.block_on(src.read_to_end(&mut rest)).unwrap();
+        assert_eq!(read.unwrap(), 0);
+    }
+}
@@ -1,5 +1,9 @@
 #![allow(clippy::result_unit_err, clippy::type_complexity)]
 
+use std::time::Duration;
+
+use super::ErrorCode;
+use super::FieldsErrorKind;
 use super::Method;
 use super::Scheme;
 use super::StreamError;
@@ -13,6 +17,17 @@ pub trait WasiPoll: WasiPollable {
     fn poll(pollables: &[&Self]) -> Vec<u32>;
 }
 
+pub trait WasiMonotonicClock: WasiPollable {
+    fn now() -> u64;
+    fn resolution() -> u64;
+    fn subscribe_instant(when: u64) -> Self
+    where
+        Self: Sized;
+    fn subscribe_duration(when: u64) -> Self
+    where
+        Self: Sized;
+}
+
 pub trait WasiSubscribe: Unpin {
     type Pollable: WasiPollable;
 
@@ -21,6 +36,7 @@ pub trait WasiSubscribe: Unpin {
 
 pub trait WasiError: Unpin {
     fn to_debug_string(&self) -> String;
+    fn http_error_code(&self) -> Option<ErrorCode>;
 }
 
 pub trait WasiStreamError: Unpin {
@@ -33,6 +49,9 @@ pub trait WasiInputStream: WasiSubscribe {
     type StreamError: WasiStreamError;
 
     fn read(&self, len: u64) -> Result<Vec<u8>, Self::StreamError>;
+    fn blocking_read(&self, len: u64) -> Result<Vec<u8>, Self::StreamError>;
+    fn skip(&self, len: u64) -> Result<u64, Self::StreamError>;
+    fn blocking_skip(&self, len: u64) -> Result<u64, Self::StreamError>;
 }
 
 pub trait WasiOutputStream: WasiSubscribe {
@@ -43,9 +62,18 @@ pub trait WasiOutputStream: WasiSubscribe {
     fn write(&self, contents: &[u8]) -> Result<(), Self::StreamError>;
     fn splice(&self, src: &Self::InputStream, len: u64) -> Result<u64, Self::StreamError>;
     fn flush(&self) -> Result<(), Self::StreamError>;
+    fn blocking_write_and_flush(&self, contents: &[u8]) -> Result<(), Self::StreamError>;
+    fn blocking_flush(&self) -> Result<(), Self::StreamError>;
+    fn blocking_splice(&self, src: &Self::InputStream, len: u64) -> Result<u64, Self::StreamError>;
+    fn write_zeroes(&self, len: u64) -> Result<(), Self::StreamError>;
 }
 
-pub trait WasiErrorCode: std::error::Error + Unpin {}
+pub trait WasiErrorCode: std::error::Error + Unpin {
+    fn to_error_code(&self) -> ErrorCode;
+    fn from_error_code(code: ErrorCode) -> Self
+    where
+        Self: Sized;
+}
 
 pub trait WasiMethod: Unpin {
     fn from_method(method: Method) -> Self
@@ -63,11 +91,22 @@ pub trait WasiScheme: Unpin {
     fn into_scheme(self) -> Scheme;
 }
 
+pub trait WasiHeaderError: std::error::Error + Unpin {
+    fn kind(&self) -> FieldsErrorKind;
+}
+
 pub trait WasiFields: Sized {
-    type Error: std::error::Error + Unpin;
+    type Error: WasiHeaderError;
 
+    fn new() -> Self;
     fn from_list(entries: &[(String, Vec<u8>)]) -> Result<Self, Self::Error>;
+    fn get(&self, name: &str) -> Vec<Vec<u8>>;
+    fn has(&self, name: &str) -> bool;
+    fn set(&self, name: &str, values: &[Vec<u8>]) -> Result<(), Self::Error>;
+    fn append(&self, name: &str, value: &[u8]) -> Result<(), Self::Error>;
+    fn delete(&self, name: &str) -> Result<(), Self::Error>;
     fn entries(&self) -> Vec<(String, Vec<u8>)>;
+    fn clone(&self) -> Self;
 }
 
 pub trait WasiIncomingBody: Unpin {
@@ -83,7 +122,7 @@ pub trait WasiFutureTrailers: WasiSubscribe {
     type Trailers: WasiFields;
     type ErrorCode: WasiErrorCode;
 
-    fn get(&self) -> Option<Result<Option<Self::Trailers>, Self::ErrorCode>>;
+    fn get(&self) -> Option<Result<Result<Option<Self::Trailers>, Self::ErrorCode>, ()>>;
 }
 
 pub trait WasiIncomingRequest: Unpin {
@@ -135,6 +174,11 @@ pub trait WasiOutgoingRequest: Unpin {
     where
         Self: Sized;
     fn body(&self) -> Result<Self::OutgoingBody, ()>;
+    fn method(&self) -> Self::Method;
+    fn path_with_query(&self) -> Option<String>;
+    fn scheme(&self) -> Option<Self::Scheme>;
+    fn authority(&self) -> Option<String>;
+    fn headers(&self) -> Self::Headers;
     fn set_method(&self, method: &Self::Method) -> Result<(), ()>;
     fn set_path_with_query(&self, path_with_query: Option<&str>) -> Result<(), ()>;
     fn set_scheme(&self, scheme: Option<&Self::Scheme>) -> Result<(), ()>;
@@ -148,6 +192,8 @@ pub trait WasiOutgoingResponse: Unpin {
     fn new(headers: Self::Headers) -> Self
     where
         Self: Sized;
+    fn status_code(&self) -> u16;
+    fn headers(&self) -> Self::Headers;
     fn set_status_code(&self, status_code: u16) -> Result<(), ()>;
     fn body(&self) -> Result<Self::OutgoingBody, ()>;
 }
@@ -159,8 +205,18 @@ pub trait WasiResponseOutparam {
     fn set(self, response: Result<Self::OutgoingResponse, &Self::ErrorCode>);
 }
 
+pub trait WasiRequestOptions: Unpin {
+    fn new() -> Self
+    where
+        Self: Sized;
+
+    fn set_connect_timeout(&self, timeout: Option<Duration>) -> Result<(), ()>;
+    fn set_first_byte_timeout(&self, timeout: Option<Duration>) -> Result<(), ()>;
+    fn set_between_bytes_timeout(&self, timeout: Option<Duration>) -> Result<(), ()>;
+}
+
 pub trait WasiOutgoingHandler: WasiOutgoingRequest + Sized {
-    type RequestOptions;
+    type RequestOptions: WasiRequestOptions;
     type FutureIncomingResponse: WasiFutureIncomingResponse;
     type ErrorCode: WasiErrorCode;
 
@@ -169,3 +225,9 @@ pub trait WasiOutgoingHandler: WasiOutgoingRequest + Sized {
         options: Option<Self::RequestOptions>,
     ) -> Result<Self::FutureIncomingResponse, Self::ErrorCode>;
 }
+
+/// Names the outgoing request type of the bindings a pollable type was
+/// generated with, so it can be inferred from a registry.
+pub trait WasiHttpBindings: WasiPollable {
+    type OutgoingRequest: WasiOutgoingHandler;
+}